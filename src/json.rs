@@ -0,0 +1,502 @@
+use std::fmt::Write;
+
+/// How deeply arrays and objects may nest before `parse` gives up, so a hand-edited file
+/// can't overflow the stack.
+const MAX_DEPTH: usize = 256;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    // Keys keep the order they were written or read in.
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Looks up `key` if this is an object.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Serializes with two-space indentation, so saved files diff nicely.
+    pub fn to_pretty_string(&self) -> String {
+        let mut out = String::new();
+        write_value(&mut out, self, 0);
+        out.push('\n');
+        out
+    }
+}
+
+fn write_value(out: &mut String, value: &Json, indent: usize) {
+    match value {
+        Json::Null => out.push_str("null"),
+        Json::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Json::Number(n) => {
+            // JSON has no NaN or infinity.
+            if n.is_finite() {
+                let _ = write!(out, "{}", n);
+            } else {
+                out.push_str("null");
+            }
+        }
+        Json::String(s) => write_string(out, s),
+        Json::Array(items) if items.is_empty() => out.push_str("[]"),
+        Json::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                out.push_str(if i == 0 { "\n" } else { ",\n" });
+                push_indent(out, indent + 1);
+                write_value(out, item, indent + 1);
+            }
+            out.push('\n');
+            push_indent(out, indent);
+            out.push(']');
+        }
+        Json::Object(fields) if fields.is_empty() => out.push_str("{}"),
+        Json::Object(fields) => {
+            out.push('{');
+            for (i, (key, item)) in fields.iter().enumerate() {
+                out.push_str(if i == 0 { "\n" } else { ",\n" });
+                push_indent(out, indent + 1);
+                write_string(out, key);
+                out.push_str(": ");
+                write_value(out, item, indent + 1);
+            }
+            out.push('\n');
+            push_indent(out, indent);
+            out.push('}');
+        }
+    }
+}
+
+fn push_indent(out: &mut String, indent: usize) {
+    for _ in 0..indent {
+        out.push_str("  ");
+    }
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Parses a complete JSON document. Errors name the byte offset where parsing stopped.
+pub fn parse(text: &str) -> Result<Json, String> {
+    let mut parser = Parser {
+        bytes: text.as_bytes(),
+        pos: 0,
+        depth: 0,
+    };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.pos != parser.bytes.len() {
+        return Err(parser.error("trailing characters"));
+    }
+    Ok(value)
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    // Arrays and objects currently open around `pos`.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> String {
+        format!("{} at byte {}", message, self.pos)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", byte as char)))
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        if self.bytes[self.pos..].starts_with(word.as_bytes()) {
+            self.pos += word.len();
+            Ok(value)
+        } else {
+            Err(self.error("unexpected token"))
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'n') => self.literal("null", Json::Null),
+            Some(b't') => self.literal("true", Json::Bool(true)),
+            Some(b'f') => self.literal("false", Json::Bool(false)),
+            Some(b'"') => Ok(Json::String(self.string()?)),
+            Some(b'[') => self.nested(Self::array),
+            Some(b'{') => self.nested(Self::object),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    /// Runs `parse` on an array or object one level deeper, refusing past `MAX_DEPTH`.
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Json, String>) -> Result<Json, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err(self.error("nested too deeply"));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    /// Reads a number in JSON's grammar: an optional minus, an integer part with no
+    /// leading zeros, then an optional fraction and exponent.
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.pos;
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        match self.peek() {
+            Some(b'0') => self.pos += 1,
+            Some(b'1'..=b'9') => self.skip_digits(),
+            _ => return Err(self.error("invalid number")),
+        }
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            self.required_digits()?;
+        }
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            self.pos += 1;
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            self.required_digits()?;
+        }
+        // The scanned range is ASCII, so it is valid UTF-8.
+        let text = std::str::from_utf8(&self.bytes[start..self.pos]).unwrap_or("");
+        text.parse()
+            .map(Json::Number)
+            .map_err(|_| self.error("invalid number"))
+    }
+
+    fn skip_digits(&mut self) {
+        while matches!(self.peek(), Some(b'0'..=b'9')) {
+            self.pos += 1;
+        }
+    }
+
+    /// Skips one or more digits; a fraction or exponent with none is an error.
+    fn required_digits(&mut self) -> Result<(), String> {
+        if !matches!(self.peek(), Some(b'0'..=b'9')) {
+            return Err(self.error("invalid number"));
+        }
+        self.skip_digits();
+        Ok(())
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect(b'"')?;
+        let mut bytes = Vec::new();
+        loop {
+            let Some(byte) = self.peek() else {
+                return Err(self.error("unterminated string"));
+            };
+            self.pos += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let Some(escape) = self.peek() else {
+                        return Err(self.error("unterminated string"));
+                    };
+                    self.pos += 1;
+                    let c = match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    let mut buf = [0; 4];
+                    bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                }
+                _ => bytes.push(byte),
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| self.error("invalid UTF-8 in string"))
+    }
+
+    /// Reads the four hex digits after `\u`, and a second `\u` escape when the first is
+    /// the high half of a surrogate pair. Unpaired surrogates become U+FFFD.
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.hex4()?;
+        if (0xD800..0xDC00).contains(&high) && self.bytes[self.pos..].starts_with(b"\\u") {
+            let saved = self.pos;
+            self.pos += 2;
+            let low = self.hex4()?;
+            if (0xDC00..0xE000).contains(&low) {
+                let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+                return Ok(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+            }
+            // Not a low half; it is read again as an escape of its own.
+            self.pos = saved;
+        }
+        Ok(char::from_u32(high).unwrap_or(char::REPLACEMENT_CHARACTER))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let digits = self
+            .bytes
+            .get(self.pos..self.pos + 4)
+            .and_then(|d| std::str::from_utf8(d).ok())
+            .filter(|d| d.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|d| u32::from_str_radix(d, 16).ok())
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        self.pos += 4;
+        Ok(digits)
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.expect(b'[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Json::Array(items));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.expect(b'{')?;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.expect(b':')?;
+            fields.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(Json::Object(fields));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(s: &str) -> Json {
+        Json::String(s.to_string())
+    }
+
+    #[test]
+    fn round_trips_nested_values() {
+        let value = Json::Object(vec![
+            (
+                "name".to_string(),
+                string("quote \" slash \\ tab \t line \n"),
+            ),
+            (
+                "items".to_string(),
+                Json::Array(vec![
+                    Json::Number(1.0),
+                    Json::Number(-2.5e-3),
+                    Json::Bool(true),
+                    Json::Null,
+                    Json::Array(vec![]),
+                    Json::Object(vec![]),
+                    Json::Array(vec![Json::Object(vec![(
+                        "deep".to_string(),
+                        Json::Array(vec![string("\u{1}control"), string("é😀")]),
+                    )])]),
+                ]),
+            ),
+        ]);
+        assert_eq!(parse(&value.to_pretty_string()), Ok(value));
+    }
+
+    #[test]
+    fn writes_non_finite_numbers_as_null() {
+        let value = Json::Array(vec![Json::Number(f64::NAN), Json::Number(f64::INFINITY)]);
+        assert_eq!(
+            parse(&value.to_pretty_string()),
+            Ok(Json::Array(vec![Json::Null, Json::Null]))
+        );
+    }
+
+    #[test]
+    fn parses_escapes() {
+        assert_eq!(
+            parse(r#""a\"b\\c\/d\b\f\n\r\t\u0041\u00e9""#),
+            Ok(string("a\"b\\c/d\u{8}\u{c}\n\r\tAé"))
+        );
+        assert_eq!(
+            parse(r#""\x""#),
+            Err("invalid escape at byte 3".to_string())
+        );
+        assert_eq!(
+            parse(r#""\u00g1""#),
+            Err("invalid unicode escape at byte 3".to_string())
+        );
+    }
+
+    #[test]
+    fn parses_surrogate_pairs() {
+        assert_eq!(parse(r#""\ud83d\ude00""#), Ok(string("😀")));
+        assert_eq!(parse(r#""\ud83dx""#), Ok(string("\u{fffd}x")));
+        assert_eq!(parse(r#""\ude00""#), Ok(string("\u{fffd}")));
+        assert_eq!(parse(r#""\ud83d\u0041""#), Ok(string("\u{fffd}A")));
+    }
+
+    #[test]
+    fn follows_the_number_grammar() {
+        for (text, expected) in [
+            ("0", 0.0),
+            ("-0", 0.0),
+            ("10", 10.0),
+            ("1.5e-3", 1.5e-3),
+            ("2E+2", 200.0),
+            ("-0.25", -0.25),
+        ] {
+            assert_eq!(parse(text), Ok(Json::Number(expected)), "{}", text);
+        }
+        for (text, error) in [
+            ("01", "trailing characters at byte 1"),
+            ("1-2", "trailing characters at byte 1"),
+            ("--1", "invalid number at byte 1"),
+            ("-", "invalid number at byte 1"),
+            ("1.", "invalid number at byte 2"),
+            ("1.e5", "invalid number at byte 2"),
+            ("1e", "invalid number at byte 2"),
+            ("1e+", "invalid number at byte 3"),
+            ("+1", "unexpected character at byte 0"),
+            (".5", "unexpected character at byte 0"),
+            ("[01]", "expected ',' or ']' at byte 2"),
+        ] {
+            assert_eq!(parse(text), Err(error.to_string()), "{}", text);
+        }
+    }
+
+    #[test]
+    fn reports_error_offsets() {
+        assert_eq!(
+            parse("[1, 2] x"),
+            Err("trailing characters at byte 7".to_string())
+        );
+        assert_eq!(
+            parse("\"abc"),
+            Err("unterminated string at byte 4".to_string())
+        );
+        assert_eq!(
+            parse("{\"a\": 1 \"b\": 2}"),
+            Err("expected ',' or '}' at byte 8".to_string())
+        );
+        assert_eq!(
+            parse("[1,]"),
+            Err("unexpected character at byte 3".to_string())
+        );
+        assert_eq!(
+            parse(""),
+            Err("unexpected end of input at byte 0".to_string())
+        );
+    }
+
+    #[test]
+    fn limits_nesting_depth() {
+        let nested = |depth| "[".repeat(depth) + &"]".repeat(depth);
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(
+            parse(&nested(MAX_DEPTH + 1)),
+            Err(format!("nested too deeply at byte {}", MAX_DEPTH))
+        );
+        let objects = "{\"a\":".repeat(MAX_DEPTH + 1);
+        assert!(parse(&objects).is_err_and(|e| e.starts_with("nested too deeply")));
+    }
+}
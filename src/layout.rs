@@ -0,0 +1,404 @@
+use crate::sketch::SceneObject;
+use raylib::prelude::Vector2;
+use std::collections::HashMap;
+
+/// Which algorithm `compute_layout` places nodes with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LayoutDirection {
+    LeftRight,
+    TopDown,
+    // Roots at the centre, each depth on a ring `x_spacing` further out.
+    Radial,
+}
+
+/// Lays out every root of `objects` in `direction`, then applies manual position
+/// overrides and, with `inherit_rotation`, swings children around their parents. This
+/// is the only entry point to the layout, so hit testing, drawing and export always
+/// agree on where nodes are.
+pub fn compute_layout(
+    objects: &[SceneObject],
+    x_spacing: f32,
+    y_spacing: f32,
+    direction: LayoutDirection,
+    inherit_rotation: bool,
+) -> HashMap<u32, Vector2> {
+    const ROOT_X: f32 = 200.0;
+    const ROOT_Y: f32 = 100.0;
+    let mut positions = HashMap::new();
+    match direction {
+        LayoutDirection::LeftRight | LayoutDirection::TopDown => {
+            // Roots stack below each other, each starting where the last one's lowest
+            // node ends. A lone leaf root sits at ROOT_Y.
+            let mut offsets = HashMap::new();
+            let mut cursor = ROOT_Y - y_spacing / 2.0;
+            for root_obj in objects {
+                let contour = layout_recursive(root_obj, y_spacing, &mut offsets);
+                let top = contour.iter().map(|level| level.0).fold(0.0, f32::min);
+                let bottom = contour.iter().map(|level| level.1).fold(0.0, f32::max);
+                let root_y = cursor - top;
+                place_subtree(
+                    root_obj,
+                    Vector2::new(ROOT_X, root_y),
+                    x_spacing,
+                    &offsets,
+                    &mut positions,
+                );
+                cursor = root_y + bottom;
+            }
+            if direction == LayoutDirection::TopDown {
+                for pos in positions.values_mut() {
+                    *pos = Vector2::new(pos.y, pos.x);
+                }
+            }
+        }
+        LayoutDirection::Radial => {
+            // Roots sit side by side, each with room for its outermost ring.
+            let mut left = ROOT_X;
+            for root_obj in objects {
+                let radius = (visible_depth(root_obj) - 1) as f32 * x_spacing;
+                let center = Vector2::new(left + radius, ROOT_Y + radius);
+                layout_radial(
+                    root_obj,
+                    center,
+                    0,
+                    0.0,
+                    std::f32::consts::TAU,
+                    x_spacing,
+                    &mut positions,
+                );
+                left += radius * 2.0 + x_spacing;
+            }
+        }
+    }
+    apply_position_overrides(objects, &mut positions);
+    if inherit_rotation {
+        let layout = positions.clone();
+        for root_obj in objects {
+            orbit_children(root_obj, &layout, &mut positions);
+        }
+    }
+    positions
+}
+
+/// Swings each child of `obj` around it by the parent's drawn angle, keeping the offset
+/// the layout gave it, like a scene graph transform. Nodes with a manual position stay
+/// put, though their own children still orbit them.
+fn orbit_children(
+    obj: &SceneObject,
+    layout: &HashMap<u32, Vector2>,
+    positions: &mut HashMap<u32, Vector2>,
+) {
+    let (Some(&base), Some(&world)) = (layout.get(&obj.id), positions.get(&obj.id)) else {
+        return;
+    };
+    for child in visible_children(obj) {
+        if child.position.is_none()
+            && let Some(&child_base) = layout.get(&child.id)
+        {
+            positions.insert(
+                child.id,
+                world + rotate_point(child_base - base, obj.angle()),
+            );
+        }
+        orbit_children(child, layout, positions);
+    }
+}
+
+/// Moves laid-out nodes that have a manual `position` to it.
+fn apply_position_overrides(objects: &[SceneObject], positions: &mut HashMap<u32, Vector2>) {
+    for obj in objects {
+        if let Some(pos) = obj.position
+            && let Some(slot) = positions.get_mut(&obj.id)
+        {
+            *slot = pos;
+        }
+        apply_position_overrides(&obj.children, positions);
+    }
+}
+
+/// Places `obj` on the ring for `depth` in the middle of its angular slice, then splits
+/// the slice between its children by how many leaves each one has.
+fn layout_radial(
+    obj: &SceneObject,
+    center: Vector2,
+    depth: usize,
+    start_angle: f32,
+    sweep: f32,
+    ring_spacing: f32,
+    positions: &mut HashMap<u32, Vector2>,
+) {
+    let angle = start_angle + sweep / 2.0;
+    let radius = depth as f32 * ring_spacing;
+    positions.insert(
+        obj.id,
+        center + Vector2::new(angle.cos(), angle.sin()) * radius,
+    );
+
+    let total_leaves = visible_leaf_count(obj) as f32;
+    let mut child_start = start_angle;
+    for child in visible_children(obj) {
+        let child_sweep = sweep * visible_leaf_count(child) as f32 / total_leaves;
+        layout_radial(
+            child,
+            center,
+            depth + 1,
+            child_start,
+            child_sweep,
+            ring_spacing,
+            positions,
+        );
+        child_start += child_sweep;
+    }
+}
+
+/// Children that take part in the layout; none for a collapsed node once they have slid
+/// back into it.
+pub fn visible_children(obj: &SceneObject) -> &[SceneObject] {
+    if obj.collapsed && obj.expand_progress <= 0.0 {
+        &[]
+    } else {
+        &obj.children
+    }
+}
+
+fn visible_leaf_count(obj: &SceneObject) -> usize {
+    let children = visible_children(obj);
+    if children.is_empty() {
+        1
+    } else {
+        children.iter().map(visible_leaf_count).sum()
+    }
+}
+
+/// Number of laid-out levels, counting `obj` itself.
+fn visible_depth(obj: &SceneObject) -> usize {
+    1 + visible_children(obj)
+        .iter()
+        .map(visible_depth)
+        .max()
+        .unwrap_or(0)
+}
+
+/// The layout algorithm for a left-to-right tree, a tidy tree in the Reingold–Tilford
+/// style. Each child's subtree is laid out, then slid down against the siblings above
+/// it until it clears them at every depth, and `obj` is centred between its first and
+/// last child. Each child's y offset from its parent goes in `offsets`. Returns the
+/// subtree's contour: its (top, bottom) extent at each depth, relative to `obj`.
+pub fn layout_recursive(
+    obj: &SceneObject,
+    y_spacing: f32,
+    offsets: &mut HashMap<u32, f32>,
+) -> Vec<(f32, f32)> {
+    // Neighbouring nodes sit `y_spacing` apart, or further when scaled-up shapes need it.
+    const MIN_GAP: f32 = 10.0;
+    let half = y_spacing.max(obj.size() + MIN_GAP) / 2.0;
+
+    // Outline of the children placed so far, from the depth below `obj` down.
+    let mut below: Vec<(f32, f32)> = Vec::new();
+    let mut child_offsets = Vec::new();
+    for child in visible_children(obj) {
+        let contour = layout_recursive(child, y_spacing, offsets);
+        let offset = if below.is_empty() {
+            0.0
+        } else {
+            below
+                .iter()
+                .zip(&contour)
+                .map(|(&(_, bottom), &(top, _))| bottom - top)
+                .fold(f32::MIN, f32::max)
+        };
+        for (level, &(top, bottom)) in contour.iter().enumerate() {
+            let (top, bottom) = (top + offset, bottom + offset);
+            match below.get_mut(level) {
+                Some(extent) => *extent = (extent.0.min(top), extent.1.max(bottom)),
+                None => below.push((top, bottom)),
+            }
+        }
+        child_offsets.push((child.id, offset));
+    }
+
+    let middle = match (child_offsets.first(), child_offsets.last()) {
+        (Some((_, first)), Some((_, last))) => (first + last) / 2.0,
+        _ => 0.0,
+    };
+    for (id, offset) in child_offsets {
+        offsets.insert(id, offset - middle);
+    }
+    let mut contour = vec![(-half, half)];
+    contour.extend(
+        below
+            .into_iter()
+            .map(|(top, bottom)| (top - middle, bottom - middle)),
+    );
+    contour
+}
+
+/// Turns the offsets from `layout_recursive` into positions, one `x_spacing` column
+/// per depth.
+fn place_subtree(
+    obj: &SceneObject,
+    pos: Vector2,
+    x_spacing: f32,
+    offsets: &HashMap<u32, f32>,
+    positions: &mut HashMap<u32, Vector2>,
+) {
+    positions.insert(obj.id, pos);
+    for child in visible_children(obj) {
+        let offset = offsets.get(&child.id).copied().unwrap_or(0.0);
+        let child_pos = Vector2::new(pos.x + x_spacing, pos.y + offset);
+        place_subtree(child, child_pos, x_spacing, offsets, positions);
+    }
+}
+
+/// Rotates `point` around the origin by `degrees`, in the same direction raylib rotates shapes.
+pub fn rotate_point(point: Vector2, degrees: f32) -> Vector2 {
+    let (sin_a, cos_a) = degrees.to_radians().sin_cos();
+    Vector2::new(
+        point.x * cos_a - point.y * sin_a,
+        point.x * sin_a + point.y * cos_a,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sketch::Shape;
+    use raylib::prelude::Color;
+
+    const X_SPACING: f32 = 150.0;
+    const Y_SPACING: f32 = 60.0;
+
+    fn node(id: u32, children: Vec<SceneObject>) -> SceneObject {
+        let mut obj = SceneObject::new(id, "", Shape::Square, Color::WHITE);
+        obj.children = children;
+        obj
+    }
+
+    /// `count` leaves with consecutive ids from `first`.
+    fn leaves(first: u32, count: u32) -> Vec<SceneObject> {
+        (first..first + count)
+            .map(|id| node(id, Vec::new()))
+            .collect()
+    }
+
+    fn layout(objects: &[SceneObject], direction: LayoutDirection) -> HashMap<u32, Vector2> {
+        compute_layout(objects, X_SPACING, Y_SPACING, direction, false)
+    }
+
+    /// Fails if two nodes in the same left-right column sit closer than `Y_SPACING`.
+    fn assert_columns_clear(positions: &HashMap<u32, Vector2>) {
+        let mut columns: HashMap<i64, Vec<f32>> = HashMap::new();
+        for pos in positions.values() {
+            columns.entry(pos.x.round() as i64).or_default().push(pos.y);
+        }
+        for (x, mut ys) in columns {
+            ys.sort_by(f32::total_cmp);
+            for pair in ys.windows(2) {
+                assert!(
+                    pair[1] - pair[0] >= Y_SPACING - 1e-3,
+                    "nodes at x={} overlap: y={} and y={}",
+                    x,
+                    pair[0],
+                    pair[1]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn parent_is_centred_between_its_first_and_last_child() {
+        let tree = node(
+            1,
+            vec![
+                node(2, leaves(3, 3)),
+                node(6, Vec::new()),
+                node(7, leaves(8, 2)),
+            ],
+        );
+        let positions = layout(std::slice::from_ref(&tree), LayoutDirection::LeftRight);
+        for parent in [&tree, &tree.children[0], &tree.children[2]] {
+            let first = positions[&parent.children[0].id];
+            let last = positions[&parent.children.last().unwrap().id];
+            let pos = positions[&parent.id];
+            assert!((pos.y - (first.y + last.y) / 2.0).abs() < 1e-3);
+            assert_eq!(first.x, pos.x + X_SPACING);
+        }
+    }
+
+    #[test]
+    fn sibling_leaves_are_one_spacing_apart() {
+        let tree = node(1, leaves(2, 2));
+        let mut offsets = HashMap::new();
+        let contour = layout_recursive(&tree, Y_SPACING, &mut offsets);
+        let half = Y_SPACING / 2.0;
+        assert_eq!(offsets[&2], -half);
+        assert_eq!(offsets[&3], half);
+        assert_eq!(contour, vec![(-half, half), (-Y_SPACING, Y_SPACING)]);
+    }
+
+    #[test]
+    fn collapsed_children_leave_the_layout_once_slid_in() {
+        let mut tree = node(1, vec![node(2, leaves(3, 2))]);
+        tree.children[0].collapsed = true;
+        tree.children[0].expand_progress = 0.5;
+        let positions = layout(std::slice::from_ref(&tree), LayoutDirection::LeftRight);
+        assert!(positions.contains_key(&3) && positions.contains_key(&4));
+
+        tree.children[0].expand_progress = 0.0;
+        let positions = layout(std::slice::from_ref(&tree), LayoutDirection::LeftRight);
+        assert!(positions.contains_key(&2));
+        assert!(!positions.contains_key(&3) && !positions.contains_key(&4));
+    }
+
+    #[test]
+    fn top_down_swaps_the_left_right_axes() {
+        let tree = node(1, vec![node(2, leaves(3, 3)), node(6, leaves(7, 2))]);
+        let left_right = layout(std::slice::from_ref(&tree), LayoutDirection::LeftRight);
+        let top_down = layout(std::slice::from_ref(&tree), LayoutDirection::TopDown);
+        assert_eq!(left_right.len(), top_down.len());
+        for (id, pos) in left_right {
+            assert_eq!(top_down[&id], Vector2::new(pos.y, pos.x));
+        }
+    }
+
+    #[test]
+    fn radial_rings_are_one_spacing_apart_per_depth() {
+        let tree = node(1, vec![node(2, leaves(3, 3)), node(6, Vec::new())]);
+        let positions = layout(std::slice::from_ref(&tree), LayoutDirection::Radial);
+        let center = positions[&1];
+        for (id, depth) in [(2, 1.0), (6, 1.0), (3, 2.0), (4, 2.0), (5, 2.0)] {
+            let radius = (positions[&id] - center).length();
+            assert!((radius - depth * X_SPACING).abs() < 1e-3, "node {}", id);
+        }
+    }
+
+    #[test]
+    fn deep_narrow_subtree_beside_shallow_wide_one_does_not_overlap() {
+        // A chain that fans out four levels down, a fan one level down, then the chain
+        // again, so each neighbour pair shares only some of their depths.
+        let chain = |first: u32| {
+            node(
+                first,
+                vec![node(
+                    first + 1,
+                    vec![node(first + 2, vec![node(first + 3, leaves(first + 4, 5))])],
+                )],
+            )
+        };
+        let tree = node(1, vec![chain(10), node(20, leaves(21, 6)), chain(30)]);
+        assert_columns_clear(&layout(
+            std::slice::from_ref(&tree),
+            LayoutDirection::LeftRight,
+        ));
+    }
+
+    #[test]
+    fn stacked_roots_do_not_overlap() {
+        let roots = [
+            node(1, vec![node(2, leaves(3, 4))]),
+            node(10, Vec::new()),
+            node(20, vec![node(21, Vec::new()), node(22, leaves(23, 3))]),
+        ];
+        assert_columns_clear(&layout(&roots, LayoutDirection::LeftRight));
+    }
+}
@@ -2,44 +2,124 @@ use glam::UVec2;
 use raylib::prelude::*;
 use raylib::{ffi::SetTraceLogLevel, prelude::TraceLogLevel};
 
+mod json;
+mod layout;
 mod sketch;
 
 const TIMESTEP: f32 = 1.0 / sketch::FRAMES_PER_SECOND as f32;
+// Steps one frame may run at normal speed, scaled up with the time scale. Time left over
+// after a stall (a minimized window, a breakpoint) is dropped rather than caught up on,
+// which would otherwise make every following frame slower still.
+const MAX_STEPS_PER_FRAME: u32 = 5;
+
+// 4x MSAA smooths the edges of rotated shapes and circles for some GPU fill rate.
+// Set SCENE_TREE_MSAA=0 to turn it off on slow machines.
+const MSAA_DEFAULT: bool = true;
+
+// Set SCENE_TREE_SEED to a number to start with a random tree of this many nodes instead
+// of the sample one. The same seed always gives the same tree.
+const RANDOM_TREE_NODES: u32 = 40;
 
 fn main() {
     // --- Raylib and Window Initialization ---
-    let (mut rl, rlt) = raylib::init().title("raylib-rs Scene Tree").build();
+    let (mut rl, rlt) = init_window(msaa_enabled());
     unsafe {
         SetTraceLogLevel(TraceLogLevel::LOG_WARNING as i32);
     }
-    let window_dims = UVec2::new(1280, 720);
+    let session = sketch::load_session(sketch::SESSION_PATH);
+    let window_dims = match &session {
+        Some(session) => UVec2::new(session.window_width as u32, session.window_height as u32),
+        None => UVec2::new(1280, 720),
+    };
     rl.set_window_size(window_dims.x as i32, window_dims.y as i32);
     center_window(&mut rl, window_dims.x as i32, window_dims.y as i32);
 
     // --- State Initialization ---
     // The state now contains our scene objects and camera.
-    let mut state = sketch::State::new();
+    let mut state = match random_tree_seed() {
+        Some(seed) => sketch::State::with_random_tree(seed, RANDOM_TREE_NODES),
+        None => sketch::State::new(),
+    };
+    if let Some(session) = &session {
+        state.restore_session(session);
+    }
+    // The window may not have taken the requested size, so centre on what it has.
+    state.set_screen_size(screen_size(&rl));
 
     // --- Main Game Loop ---
     while !rl.window_should_close() {
+        state.steps_this_frame = 0;
+        // Escape cancels text edits, closes the help, the find bar and the quick-add
+        // palette, so it only quits when none of them is open.
+        rl.set_exit_key(match state.active_textbox_id {
+            Some(_) => None,
+            None if state.show_help || state.search_focused || state.quick_add.is_some() => None,
+            None => Some(KeyboardKey::KEY_ESCAPE),
+        });
+
         // Process inputs from the user
         sketch::process_events_and_input(&mut rl, &mut state);
 
+        // Exports render offscreen, so they run before the frame begins.
+        if state.export_requested {
+            state.export_requested = false;
+            sketch::export_png(&mut rl, &rlt, &state, "tree.png");
+        }
+
         // --- Fixed Timestep Update Logic ---
         let dt = rl.get_frame_time();
-        state.time_since_last_update += dt;
+        sketch::update_camera(&mut state, dt, screen_size(&rl));
+        state.time_since_last_update += dt * state.time_scale;
+        let max_steps = (MAX_STEPS_PER_FRAME as f32 * state.time_scale.max(1.0)).ceil() as u32;
         while state.time_since_last_update > TIMESTEP {
+            if state.steps_this_frame == max_steps {
+                state.time_since_last_update = 0.0;
+                break;
+            }
             state.time_since_last_update -= TIMESTEP;
             sketch::step(&mut state, TIMESTEP);
+            state.steps_this_frame += 1;
         }
 
         // --- Drawing Logic ---
         let mut draw_handle = rl.begin_drawing(&rlt);
-        draw_handle.clear_background(Color::from_hex("222222").unwrap());
+        draw_handle.clear_background(state.theme.background);
 
         // The main draw function handles both world-space and screen-space rendering.
         sketch::draw(&mut state, &mut draw_handle);
     }
+
+    // The loop only ends on a normal close, so this runs before the window goes away.
+    let session = state.session(rl.get_screen_width(), rl.get_screen_height());
+    if let Err(err) = sketch::save_session(&session, sketch::SESSION_PATH) {
+        println!("Couldn't save session: {}", err);
+    }
+}
+
+/// Creates the window. MSAA is only read at init time, so it's decided here. Drivers
+/// without multisampling ignore the hint and render aliased as before.
+fn init_window(msaa: bool) -> (RaylibHandle, RaylibThread) {
+    let mut builder = raylib::init();
+    builder.title("raylib-rs Scene Tree");
+    if msaa {
+        builder.msaa_4x();
+    }
+    builder.build()
+}
+
+fn msaa_enabled() -> bool {
+    match std::env::var("SCENE_TREE_MSAA") {
+        Ok(value) => value != "0",
+        Err(_) => MSAA_DEFAULT,
+    }
+}
+
+fn random_tree_seed() -> Option<u64> {
+    std::env::var("SCENE_TREE_SEED").ok()?.parse().ok()
+}
+
+fn screen_size(rl: &RaylibHandle) -> Vector2 {
+    Vector2::new(rl.get_screen_width() as f32, rl.get_screen_height() as f32)
 }
 
 /// Centers the window on the current monitor.
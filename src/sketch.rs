@@ -1,13 +1,102 @@
+use crate::json::{self, Json};
+use crate::layout::{self, LayoutDirection, rotate_point, visible_children};
+use rand::{Rng, SeedableRng, rngs::StdRng};
 use raylib::prelude::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
 
 pub const FRAMES_PER_SECOND: u32 = 60;
 
+/// Bounds for `State::time_scale`, stepped by halving and doubling.
+const MIN_TIME_SCALE: f32 = 0.125;
+const MAX_TIME_SCALE: f32 = 8.0;
+
+/// Side length (or diameter) of every node shape in world units.
+const NODE_SIZE: f32 = 40.0;
+// Range of `SceneObject::scale`, shared by the panel slider and Ctrl+wheel.
+const MIN_NODE_SCALE: f32 = 0.5;
+const MAX_NODE_SCALE: f32 = 3.0;
+/// Seconds children take to slide out of a node being expanded, or back into it.
+const COLLAPSE_ANIM_SECS: f32 = 0.2;
+
+/// Limits for every way of zooming the camera.
+const MIN_ZOOM: f32 = 0.1;
+const MAX_ZOOM: f32 = 2.0;
+
+/// Node label font size in world units. The spacing is what `draw_text` uses for the
+/// default font, so labels measure the same way whether drawn or hit-tested.
+const LABEL_FONT_SIZE: f32 = 20.0;
+const LABEL_SPACING: f32 = LABEL_FONT_SIZE / 10.0;
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Shape {
     Square,
     Circle,
     Triangle,
+    Star,
+    Hexagon,
+}
+
+/// What a node stands for. Choosing one in the settings panel applies its default look.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NodeKind {
+    // No defaults; the node keeps whatever shape and colour it was given.
+    Plain,
+    Group,
+    Data,
+    Render,
+    Asset,
+}
+
+impl NodeKind {
+    /// The shape and colour a node takes when switched to this kind. None for `Plain`.
+    fn defaults(self) -> Option<(Shape, Color)> {
+        match self {
+            NodeKind::Plain => None,
+            NodeKind::Group => Some((Shape::Hexagon, Color::GRAY)),
+            NodeKind::Data => Some((Shape::Circle, Color::SKYBLUE)),
+            NodeKind::Render => Some((Shape::Triangle, Color::LIME)),
+            NodeKind::Asset => Some((Shape::Square, Color::ORANGE)),
+        }
+    }
+}
+
+/// How parent-child connector lines are routed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConnectorStyle {
+    Straight,
+    /// Horizontal out of the parent, vertical at the midpoint, then horizontal into the child.
+    Elbow,
+    Bezier,
+}
+
+/// Which of the settings panel's text fields has focus while `active_textbox_id` is set.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PanelField {
+    Name,
+    Notes,
+    Category,
+    Glyph,
+    // A key or value cell of the metadata table, by row.
+    MetadataKey(usize),
+    MetadataValue(usize),
+}
+
+/// Where the settings panel sits and is scrolled to, kept between frames.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PanelState {
+    // Top-left corner on screen; moved by dragging the title bar.
+    pub pos: Vector2,
+    // Cursor offset from `pos` while the title bar is being dragged.
+    pub drag: Option<Vector2>,
+    // Index into `PANEL_CONTROLS` of the control Ctrl+Tab last moved the keyboard focus to.
+    pub focus_index: Option<usize>,
+    // Node the offset belongs to. Opening another node starts back at the top.
+    pub node_id: Option<u32>,
+    // raygui's scroll offset; `y` is zero or negative.
+    pub offset: Vector2,
+    // Height the panel's controls took up on the last frame they were drawn.
+    pub content_height: f32,
 }
 
 /// Represents a single object in our scene tree.
@@ -17,50 +106,418 @@ pub struct SceneObject {
     pub text: String,
     pub shape: Shape,
     pub color: Color,
+    // Only sets the shape and colour when chosen; both can be changed afterwards.
+    pub kind: NodeKind,
     pub rotation_speed: f32,
     pub current_rotation: f32,
+    // Fixed angle added to the animated rotation, in degrees. A phase when spinning.
+    pub rotation_offset: f32,
+    // Sum of the ancestors' angles while `State::inherit_rotation` is on, else zero.
+    // Set each step; not saved.
+    pub inherited_rotation: f32,
+    // Multiplies NODE_SIZE for the shape, glyph and hit test. Layout spacing ignores it.
+    pub scale: f32,
     pub children: Vec<SceneObject>,
+    // Working copy of `text` while a rename is open; it follows `text` otherwise.
     pub text_buffer: String,
+    // Hidden nodes keep their slot in the layout but only draw as an outline.
+    pub visible: bool,
+    // Collapsed nodes are laid out as leaves; their subtree is kept but not shown.
+    pub collapsed: bool,
+    // How far the children are slid out, from 0 (tucked into this node) to 1. Eases
+    // towards `collapsed` each step; not saved.
+    pub expand_progress: f32,
+    // Applied on top of `color` to the shape, label and incoming connector.
+    pub alpha: u8,
+    // Manual world position that replaces the computed one; None follows the layout.
+    pub position: Option<Vector2>,
+    // Locked nodes can't be renamed, restyled or deleted, nor can any ancestor.
+    pub locked: bool,
+    // Freeform multi-line description, edited in the settings panel.
+    pub notes: String,
+    // Freeform tag; see `State::filter_category`.
+    pub category: String,
+    // Second fill colour. When set the shape blends from `color` into it.
+    pub color2: Option<Color>,
+    // Border drawn around the shape, following its rotation. None draws no border.
+    pub outline: Option<Color>,
+    pub outline_thickness: f32,
+    // Character drawn in the fill colour in place of the shape; hit-tested as a circle.
+    // The default font only has Latin characters, so emoji show as '?'.
+    pub glyph: Option<char>,
+    // Freeform key/value pairs, in the order they were added. Keys needn't be unique.
+    pub metadata: Vec<(String, String)>,
 }
 
 impl SceneObject {
-    fn new(id: u32, text: &str, shape: Shape, color: Color) -> Self {
+    pub fn new(id: u32, text: &str, shape: Shape, color: Color) -> Self {
         Self {
             id,
             text: text.to_string(),
             shape,
             color,
+            kind: NodeKind::Plain,
             rotation_speed: 20.0,
             current_rotation: 0.0,
+            rotation_offset: 0.0,
+            inherited_rotation: 0.0,
+            scale: 1.0,
             children: Vec::new(),
             text_buffer: text.to_string(),
+            visible: true,
+            collapsed: false,
+            expand_progress: 1.0,
+            alpha: 255,
+            position: None,
+            locked: false,
+            notes: String::new(),
+            category: String::new(),
+            color2: None,
+            outline: None,
+            outline_thickness: 2.0,
+            glyph: None,
+            metadata: Vec::new(),
+        }
+    }
+
+    /// The angle the shape is drawn and hit-tested at, in degrees.
+    pub fn angle(&self) -> f32 {
+        self.current_rotation + self.rotation_offset + self.inherited_rotation
+    }
+
+    /// Side (or diameter) the shape is drawn and hit-tested at.
+    pub fn size(&self) -> f32 {
+        NODE_SIZE * self.scale
+    }
+
+    /// Pulls values the editor can't produce back into range, for this node and its
+    /// subtree. Returns how many fields were changed.
+    fn sanitize(&mut self) -> usize {
+        let mut fixed = 0;
+        let speed = if self.rotation_speed.is_finite() {
+            self.rotation_speed.clamp(-180.0, 180.0)
+        } else {
+            0.0
+        };
+        if speed != self.rotation_speed {
+            self.rotation_speed = speed;
+            fixed += 1;
+        }
+        let offset = if self.rotation_offset.is_finite() {
+            self.rotation_offset.clamp(-180.0, 180.0)
+        } else {
+            0.0
+        };
+        if offset != self.rotation_offset {
+            self.rotation_offset = offset;
+            fixed += 1;
+        }
+        let scale = if self.scale.is_finite() {
+            self.scale.clamp(MIN_NODE_SCALE, MAX_NODE_SCALE)
+        } else {
+            1.0
+        };
+        if scale != self.scale {
+            self.scale = scale;
+            fixed += 1;
+        }
+        let thickness = if self.outline_thickness.is_finite() {
+            self.outline_thickness.clamp(1.0, 10.0)
+        } else {
+            2.0
+        };
+        if thickness != self.outline_thickness {
+            self.outline_thickness = thickness;
+            fixed += 1;
+        }
+        if self
+            .glyph
+            .is_some_and(|c| c.is_control() || c.is_whitespace())
+        {
+            self.glyph = None;
+            fixed += 1;
+        }
+        if self
+            .position
+            .is_some_and(|pos| !pos.x.is_finite() || !pos.y.is_finite())
+        {
+            self.position = None;
+            fixed += 1;
         }
+        fixed
+            + self
+                .children
+                .iter_mut()
+                .map(SceneObject::sanitize)
+                .sum::<usize>()
     }
 }
 
 /// A request to modify the scene tree, processed at the end of the frame.
 #[derive(Debug, Clone)]
 pub enum EditorRequest {
-    AddChild { parent_id: u32 },
-    DeleteNode { node_id: u32 },
+    AddRoot,
+    /// With `select`, the new child is also selected and centred once it's laid out.
+    AddChild {
+        parent_id: u32,
+        select: bool,
+    },
+    /// Adds a selected child with `shape`, and `text` as its name unless that's empty.
+    /// Sent by the quick-add palette.
+    AddShapedChild {
+        parent_id: u32,
+        shape: Shape,
+        text: String,
+    },
+    /// Deletes leaves right away; if any node has children, one confirmation covers the
+    /// whole batch. Locked nodes are refused and stay selected.
+    DeleteNodes {
+        node_ids: Vec<u32>,
+    },
+    /// A `DeleteNodes` the user has agreed to in the confirmation dialog.
+    DeleteConfirmed {
+        node_ids: Vec<u32>,
+    },
+    /// Inserts a fresh copy of the clipboard under `parent_id`, or at the root level.
+    Paste {
+        parent_id: Option<u32>,
+    },
+    /// Copies a node's subtree and inserts it right after the original.
+    Duplicate {
+        node_id: u32,
+    },
+    ToggleCollapse {
+        node_id: u32,
+    },
+    /// Adds a loaded template under `parent_id`, or at the root level, with fresh ids.
+    Insert {
+        parent_id: Option<u32>,
+        subtree: SceneObject,
+    },
+    /// Swaps the whole tree for one loaded with `load_scene`.
+    ReplaceScene {
+        objects: Vec<SceneObject>,
+    },
+    SetCollapsedAll {
+        collapsed: bool,
+    },
+    /// Expands the first `depth` levels and collapses everything from there down.
+    CollapseToDepth {
+        depth: usize,
+    },
+    /// Shifts a node `delta` places among its siblings, stopping at either end.
+    MoveSibling {
+        node_id: u32,
+        delta: i32,
+    },
+    /// Lines the lasso selection up on a shared y (`horizontal`) or x, at their average.
+    /// Locked nodes are left out.
+    Align {
+        horizontal: bool,
+    },
+    /// Drops the node's manual position so the automatic layout places it again.
+    ResetPosition {
+        node_id: u32,
+    },
+    /// Keeps the node where the layout last put it, as a manual position.
+    PinPosition {
+        node_id: u32,
+    },
+    /// Dims everything outside `category`, or clears the filter with None.
+    SetCategoryFilter {
+        category: Option<String>,
+    },
+    /// Moves the nodes under a new parent, which takes the place of the first of them in
+    /// tree order. Ignored if one of the nodes is an ancestor of another.
+    Group {
+        node_ids: Vec<u32>,
+    },
+    /// Removes a node and moves its children up into its place among its siblings.
+    Ungroup {
+        node_id: u32,
+    },
+}
+
+/// A saved view, stored with Shift+1..9 and recalled with 1..9.
+#[derive(Debug, Clone, Copy)]
+pub struct Bookmark {
+    pub target: Vector2,
+    pub zoom: f32,
+    pub selected_id: Option<u32>,
+}
+
+/// Window size, view and camera smoothing, saved on exit and restored on the next start.
+#[derive(Debug, Clone, Copy)]
+pub struct Session {
+    pub window_width: i32,
+    pub window_height: i32,
+    pub camera_target: Vector2,
+    pub camera_zoom: f32,
+    pub smooth_camera: bool,
+}
+
+pub const SESSION_PATH: &str = "session.json";
+// Where Ctrl+S saves the whole tree and Ctrl+O loads it from.
+const SCENE_PATH: &str = "scene.json";
+
+/// Canvas colours, cycled with T. Panels, menus and overlays keep their own dark style.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub name: &'static str,
+    pub background: Color,
+    // Node names, canvas text and grid lines.
+    pub text: Color,
+    // Ids, lock icons, collapsed badges and hints.
+    pub muted_text: Color,
+    pub connector: Color,
+    // Fill for nodes added in the editor.
+    pub node: Color,
+}
+
+pub const THEMES: [Theme; 2] = [
+    Theme {
+        name: "dark",
+        background: Color::new(0x22, 0x22, 0x22, 255),
+        text: Color::WHITE,
+        muted_text: Color::LIGHTGRAY,
+        connector: Color::GRAY,
+        node: Color::WHITE,
+    },
+    Theme {
+        name: "light",
+        background: Color::new(0xf2, 0xf2, 0xee, 255),
+        text: Color::new(0x20, 0x20, 0x20, 255),
+        muted_text: Color::new(0x60, 0x60, 0x60, 255),
+        connector: Color::new(0x90, 0x90, 0x90, 255),
+        node: Color::new(0x3a, 0x5f, 0x8f, 255),
+    },
+];
+
+impl Theme {
+    /// The preset after this one in `THEMES`, wrapping around.
+    pub fn next(&self) -> Theme {
+        let index = THEMES.iter().position(|t| t.name == self.name).unwrap_or(0);
+        THEMES[(index + 1) % THEMES.len()]
+    }
 }
 
 /// The main state for the application.
 pub struct State {
     pub time_since_last_update: f32,
+    // Multiplies frame time before it feeds the fixed steps. Changed with [ and ].
+    pub time_scale: f32,
     pub scene_objects: Vec<SceneObject>,
     pub camera: Camera2D,
+    // Where the camera is easing towards; see `update_camera`.
+    pub camera_target_goal: Vector2,
+    pub camera_zoom_goal: f32,
+    // Screen point that wheel and key zooming centres on, until the zoom settles.
+    pub zoom_anchor: Option<Vector2>,
     next_id: u32,
     pub active_settings_id: Option<u32>,
     pub requests: Vec<EditorRequest>,
     // The ID of the text box that is currently active.
     pub active_textbox_id: Option<u32>,
+    // Which of the settings panel's fields that text box is.
+    pub panel_field: PanelField,
+    pub panel: PanelState,
+    // Set by the P key; main renders the export outside of the frame's drawing.
+    pub export_requested: bool,
+    // Subtree copied with Ctrl+C. Ids are reassigned each time it is pasted.
+    pub clipboard: Option<SceneObject>,
+    pub connector_style: ConnectorStyle,
+    // Width of an ordinary connector, in world units.
+    pub connector_thickness: f32,
+    // When set, each connector also widens with the size of the subtree it leads to.
+    pub weight_connectors: bool,
+    // When set, nodes are filled by tree depth instead of their own `color`.
+    pub color_by_depth: bool,
+    // Distance between a node and its children, and between neighbouring leaves.
+    pub x_spacing: f32,
+    pub y_spacing: f32,
+    pub layout_direction: LayoutDirection,
+    // Cached result of `compute_layout`. Set `layout_dirty` after anything that moves nodes.
+    pub layout_positions: HashMap<u32, Vector2>,
+    pub layout_dirty: bool,
+    // Nodes sliding in or out of a collapsing or expanding ancestor, with how far they
+    // have faded in. Rebuilt with the layout; empty once every animation has settled.
+    pub collapse_fades: HashMap<u32, f32>,
+    // Toggled with I; shows `TreeStats` under the view toolbar.
+    pub show_stats: bool,
+    // Toggled with backtick; see `draw_perf_overlay`.
+    pub show_perf: bool,
+    // Toggled with G; see `draw_grid`.
+    pub show_grid: bool,
+    // Toggled with N; draws each node's id above its shape.
+    pub show_ids: bool,
+    // Toggled with L; draws node names on a plate of the background colour.
+    pub label_plates: bool,
+    // Toggled with K; names turn with their node instead of staying upright.
+    pub labels_rotate: bool,
+    // Toggled with R; children turn with their parent's angle and orbit it.
+    pub inherit_rotation: bool,
+    // Fixed-update steps run this frame. Reset and counted by the main loop.
+    pub steps_this_frame: u32,
+    // Node (None for empty canvas), time and screen position of the last click, for
+    // double-click detection.
+    pub last_click: Option<(Option<u32>, f64, Vector2)>,
+    // Node whose label is being renamed in place; see `draw_inline_editor`.
+    pub inline_edit_id: Option<u32>,
+    // Node id and screen position of the open right-click menu.
+    pub context_menu: Option<(u32, Vector2)>,
+    // True while a middle-drag, or a right-drag that started on empty canvas, is panning.
+    pub panning: bool,
+    // World units per second the camera keeps moving at after a pan is released.
+    pub pan_velocity: Vector2,
+    // Toggled with M. Off, every camera move and zoom lands at once and pans don't
+    // coast, for anyone that animation bothers.
+    pub smooth_camera: bool,
+    // A request waiting on the modal confirmation dialog. Blocks all other input.
+    pub pending_confirm: Option<EditorRequest>,
+    // Short feedback shown at the bottom of the screen, and when it was set.
+    pub notice: Option<(String, Instant)>,
+    pub bookmarks: [Option<Bookmark>; 9],
+    // Nodes picked with the lasso, separate from the single node in the settings panel.
+    pub selected_ids: HashSet<u32>,
+    // World-space points of the lasso being drawn; empty when not lassoing.
+    pub lasso: Vec<Vector2>,
+    // World-space corner where an Alt-drag to zoom to a region started.
+    pub zoom_region: Option<Vector2>,
+    // Node under the cursor and how long it has been there, for the tooltip.
+    pub hover_id: Option<u32>,
+    pub hover_time: f32,
+    // Toggled with F. Left-dragging a node then moves it to a manual `position`.
+    pub free_layout: bool,
+    // Node being dragged in free layout, and the cursor's world offset from its centre.
+    pub dragging: Option<(u32, Vector2)>,
+    // Toggled with F1 or ?; see `draw_help_overlay`. Blocks other input while open.
+    pub show_help: bool,
+    // How far the help list is scrolled down, in pixels.
+    pub help_scroll: f32,
+    // When set, nodes of other categories are dimmed, except ancestors of matches.
+    pub filter_category: Option<String>,
+    // Node to select and centre on at the next input pass, once it has a layout slot.
+    pub focus_pending: Option<u32>,
+    pub theme: Theme,
+    // Screen areas the last frame covered with GUI. Clicks, hovers and the wheel over
+    // them don't reach the world; see `over_gui`.
+    pub gui_rects: Vec<Rectangle>,
+    // Find bar, opened with Ctrl+F. Typing there blocks shortcuts like a text box.
+    pub search_focused: bool,
+    pub search_query: String,
+    // Nodes whose name contains the query, ignoring case. Recomputed as it changes.
+    pub search_matches: HashSet<u32>,
+    // Index into the matches in tree order of the one Enter last moved to.
+    pub search_cursor: Option<usize>,
+    // Line typed into the quick-add palette while it's open (Tab); see `parse_quick_add`.
+    pub quick_add: Option<String>,
 }
 
 impl State {
     pub fn new() -> Self {
         let mut state = Self {
             time_since_last_update: 0.0,
+            time_scale: 1.0,
             scene_objects: Vec::new(),
             camera: Camera2D {
                 target: Vector2::new(400.0, 450.0),
@@ -68,10 +525,65 @@ impl State {
                 rotation: 0.0,
                 zoom: 1.0,
             },
+            camera_target_goal: Vector2::new(400.0, 450.0),
+            camera_zoom_goal: 1.0,
+            zoom_anchor: None,
             next_id: 0,
             active_settings_id: None,
             requests: Vec::new(),
             active_textbox_id: None,
+            panel_field: PanelField::Name,
+            panel: PanelState {
+                pos: Vector2::new(20.0, 20.0),
+                ..Default::default()
+            },
+            export_requested: false,
+            clipboard: None,
+            connector_style: ConnectorStyle::Straight,
+            connector_thickness: 1.0,
+            weight_connectors: false,
+            color_by_depth: false,
+            x_spacing: 250.0,
+            y_spacing: 120.0,
+            layout_direction: LayoutDirection::LeftRight,
+            layout_positions: HashMap::new(),
+            layout_dirty: true,
+            collapse_fades: HashMap::new(),
+            show_stats: false,
+            show_perf: false,
+            show_grid: false,
+            show_ids: false,
+            label_plates: true,
+            labels_rotate: false,
+            inherit_rotation: false,
+            steps_this_frame: 0,
+            last_click: None,
+            inline_edit_id: None,
+            context_menu: None,
+            panning: false,
+            pan_velocity: Vector2::zero(),
+            smooth_camera: true,
+            pending_confirm: None,
+            notice: None,
+            bookmarks: [None; 9],
+            selected_ids: HashSet::new(),
+            lasso: Vec::new(),
+            zoom_region: None,
+            hover_id: None,
+            hover_time: 0.0,
+            free_layout: false,
+            dragging: None,
+            show_help: false,
+            help_scroll: 0.0,
+            filter_category: None,
+            focus_pending: None,
+            theme: THEMES[0],
+            gui_rects: Vec::new(),
+            search_focused: false,
+            search_query: String::new(),
+            search_matches: HashSet::new(),
+            search_cursor: None,
+            quick_add: None,
         };
 
         // --- Create a sample scene tree ---
@@ -93,74 +605,775 @@ impl State {
         state
     }
 
+    /// Like `new`, but with a random tree of `node_count` nodes in place of the sample
+    /// one: random shapes, swatch colours and names. The same seed always builds the
+    /// same tree, which suits screenshots and layout benchmarks.
+    pub fn with_random_tree(seed: u64, node_count: u32) -> Self {
+        const SHAPES: [Shape; 5] = [
+            Shape::Square,
+            Shape::Circle,
+            Shape::Triangle,
+            Shape::Star,
+            Shape::Hexagon,
+        ];
+        const WORDS: [&str; 8] = [
+            "Data", "Render", "Mesh", "Texture", "Shader", "Audio", "Input", "Physics",
+        ];
+        let mut state = Self::new();
+        state.scene_objects.clear();
+        state.next_id = 0;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let nodes = (0..node_count)
+            .map(|_| {
+                let id = state.new_id();
+                let name = format!("{} {}", WORDS[rng.random_range(0..WORDS.len())], id);
+                let shape = SHAPES[rng.random_range(0..SHAPES.len())];
+                let color = COLOR_SWATCHES[rng.random_range(0..COLOR_SWATCHES.len())];
+                SceneObject::new(id, &name, shape, color)
+            })
+            .collect();
+        state.scene_objects = hang_randomly(nodes, &mut rng);
+        state
+    }
+
+    /// Moves the camera to where a saved session left it.
+    pub fn restore_session(&mut self, session: &Session) {
+        self.set_screen_size(Vector2::new(
+            session.window_width as f32,
+            session.window_height as f32,
+        ));
+        self.camera.target = session.camera_target;
+        self.camera.zoom = session.camera_zoom;
+        self.camera_target_goal = session.camera_target;
+        self.camera_zoom_goal = session.camera_zoom;
+        self.smooth_camera = session.smooth_camera;
+    }
+
+    /// Keeps the camera target in the middle of a window of `screen_size`.
+    pub fn set_screen_size(&mut self, screen_size: Vector2) {
+        self.camera.offset = screen_size * 0.5;
+    }
+
+    /// The current view, to save along with the window size.
+    pub fn session(&self, window_width: i32, window_height: i32) -> Session {
+        Session {
+            window_width,
+            window_height,
+            camera_target: self.camera_target_goal,
+            camera_zoom: self.camera_zoom_goal,
+            smooth_camera: self.smooth_camera,
+        }
+    }
+
+    /// The id after the last one handed out. Past `u32::MAX` it wraps round to 0, and
+    /// ids already in the tree are skipped.
     pub fn new_id(&mut self) -> u32 {
-        self.next_id += 1;
-        self.next_id
+        let taken: HashSet<u32> = all_ids(&self.scene_objects).into_iter().collect();
+        loop {
+            self.next_id = self.next_id.wrapping_add(1);
+            if !taken.contains(&self.next_id) {
+                return self.next_id;
+            }
+        }
     }
 }
 
-/// Handles user input for camera movement and object selection.
+/// Handles user input for camera movement and object selection. While a text box is
+/// being edited only mouse panning and wheel zoom stay live.
 pub fn process_events_and_input(rl: &mut RaylibHandle, state: &mut State) {
-    if state.active_textbox_id.is_some() {
+    // Reset every frame; the hover and pan checks below may pick another one.
+    rl.set_mouse_cursor(MouseCursor::MOUSE_CURSOR_DEFAULT);
+    if state.pending_confirm.is_some() {
+        return;
+    }
+    let editing =
+        state.active_textbox_id.is_some() || state.search_focused || state.quick_add.is_some();
+
+    let shift_down =
+        rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+    let help_pressed = !editing
+        && (rl.is_key_pressed(KeyboardKey::KEY_F1)
+            || (shift_down && rl.is_key_pressed(KeyboardKey::KEY_SLASH)));
+    if help_pressed || (state.show_help && rl.is_key_pressed(KeyboardKey::KEY_ESCAPE)) {
+        state.show_help = !state.show_help && help_pressed;
+        state.help_scroll = 0.0;
+    }
+    if state.show_help {
+        // The overlay clamps this to its content when it draws.
+        state.help_scroll -= rl.get_mouse_wheel_move() * 40.0;
         return;
     }
 
     const ZOOM_INCREMENT: f32 = 0.125;
-    const MIN_ZOOM: f32 = 0.1;
-    const MAX_ZOOM: f32 = 2.0;
 
-    let wheel_move = rl.get_mouse_wheel_move();
-    if wheel_move != 0.0 {
+    // Zoom input only moves the goal; `update_camera` eases towards it around the cursor.
+    let goal_before = state.camera_zoom_goal;
+    // Over the settings panel the wheel scrolls the panel instead, and other GUI
+    // ignores it.
+    let wheel_move = if over_gui(state, rl.get_mouse_position()) {
+        0.0
+    } else {
+        rl.get_mouse_wheel_move()
+    };
+    let ctrl_down = rl.is_key_down(KeyboardKey::KEY_LEFT_CONTROL)
+        || rl.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL);
+    // Ctrl+wheel resizes the selected node instead of zooming.
+    let scaled = ctrl_down
+        && wheel_move != 0.0
+        && state.active_settings_id.is_some_and(|id| {
+            find_object_by_id_mut(&mut state.scene_objects, id).is_some_and(|obj| {
+                if !obj.locked {
+                    obj.scale = (obj.scale + wheel_move.signum() * 0.1)
+                        .clamp(MIN_NODE_SCALE, MAX_NODE_SCALE);
+                }
+                true
+            })
+        });
+    if wheel_move != 0.0 && !scaled {
         let wheel_direction = if wheel_move > 0.0 { 1.0 } else { -1.0 };
-        state.camera.zoom += wheel_direction * ZOOM_INCREMENT;
+        state.camera_zoom_goal += wheel_direction * ZOOM_INCREMENT;
+    }
+    if !editing && rl.is_key_down(KeyboardKey::KEY_EQUAL) {
+        state.camera_zoom_goal += 1.0 * rl.get_frame_time();
+    }
+    if !editing && rl.is_key_down(KeyboardKey::KEY_MINUS) {
+        state.camera_zoom_goal -= 1.0 * rl.get_frame_time();
+    }
+    if !editing && rl.is_key_pressed(KeyboardKey::KEY_ZERO) {
+        state.camera_zoom_goal = 1.0;
+    }
+    state.camera_zoom_goal = state.camera_zoom_goal.clamp(MIN_ZOOM, MAX_ZOOM);
+    if state.camera_zoom_goal != goal_before {
+        state.zoom_anchor = Some(rl.get_mouse_position());
+    }
+
+    // Typing goes to the text box, so keyboard shortcuts wait until it's closed.
+    if !editing {
+        process_shortcuts(rl, state);
+    }
+
+    // Enter in the find bar moves to the next match, opening collapsed ancestors so it
+    // gets a layout slot below.
+    let mut search_target = None;
+    if state.search_focused && rl.is_key_pressed(KeyboardKey::KEY_ENTER) {
+        let mut order = all_ids(&state.scene_objects);
+        order.retain(|id| state.search_matches.contains(id));
+        if !order.is_empty() {
+            let next = state.search_cursor.map_or(0, |i| (i + 1) % order.len());
+            state.search_cursor = Some(next);
+            let id = order[next];
+            for ancestor in path_to_node(&state.scene_objects, id).unwrap_or_default() {
+                if ancestor != id
+                    && let Some(obj) = find_object_by_id_mut(&mut state.scene_objects, ancestor)
+                    && obj.collapsed
+                {
+                    obj.collapsed = false;
+                    state.layout_dirty = true;
+                }
+            }
+            search_target = Some(id);
+        }
+    }
+    if state.quick_add.is_some() && rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+        state.quick_add = None;
+    }
+    if state.search_focused && rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+        state.search_focused = false;
+        state.search_query.clear();
+        state.search_matches.clear();
+        state.search_cursor = None;
+    }
+
+    refresh_layout(state);
+    let layout_positions = &state.layout_positions;
+    // Nodes still sliding with a collapse can't be clicked until they settle.
+    let settled_positions: HashMap<u32, Vector2>;
+    let hit_positions = if state.collapse_fades.is_empty() {
+        layout_positions
+    } else {
+        settled_positions = layout_positions
+            .iter()
+            .filter(|(id, _)| !state.collapse_fades.contains_key(id))
+            .map(|(id, pos)| (*id, *pos))
+            .collect();
+        &settled_positions
+    };
+    let screen_size = Vector2::new(rl.get_screen_width() as f32, rl.get_screen_height() as f32);
+
+    let mouse_pos = rl.get_mouse_position();
+    let world_mouse_pos = screen_to_world(mouse_pos, &state.camera);
+
+    if let Some(id) = search_target
+        && let Some(obj_pos) = layout_positions.get(&id)
+    {
+        state.camera_target_goal = *obj_pos;
+    }
+    if let Some(id) = state.focus_pending.take()
+        && let Some(obj_pos) = layout_positions.get(&id)
+    {
+        state.active_settings_id = Some(id);
+        state.camera_target_goal = panel_view_target(&state.camera, *obj_pos, screen_size.x);
+    }
+
+    let hovered = node_at(&state.scene_objects, world_mouse_pos, hit_positions);
+    if hovered == state.hover_id {
+        state.hover_time += rl.get_frame_time();
+    } else {
+        state.hover_id = hovered;
+        state.hover_time = 0.0;
+    }
+
+    // Any fresh click outside the context menu dismisses it.
+    if let Some((_, menu_pos)) = state.context_menu {
+        let pressed = rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT)
+            || rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_RIGHT);
+        if check_collision_point_rec(mouse_pos, context_menu_rect(menu_pos)) {
+            // The menu's buttons handle this click during drawing.
+            return;
+        } else if pressed {
+            state.context_menu = None;
+        }
+    }
+
+    // Right-pressing a node opens its menu; anywhere else (or while editing) starts a pan.
+    if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_RIGHT) {
+        match node_at(&state.scene_objects, world_mouse_pos, hit_positions) {
+            Some(id) if !editing && !over_gui(state, mouse_pos) => {
+                state.context_menu = Some((id, mouse_pos))
+            }
+            _ => {
+                state.panning = true;
+                state.pan_velocity = Vector2::zero();
+            }
+        }
     }
-    if rl.is_key_down(KeyboardKey::KEY_EQUAL) {
-        state.camera.zoom += 1.0 * rl.get_frame_time();
+    // Middle-drag always pans, node or not.
+    if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_MIDDLE) {
+        state.panning = true;
+        state.pan_velocity = Vector2::zero();
     }
-    if rl.is_key_down(KeyboardKey::KEY_MINUS) {
-        state.camera.zoom -= 1.0 * rl.get_frame_time();
+    if !rl.is_mouse_button_down(MouseButton::MOUSE_BUTTON_RIGHT)
+        && !rl.is_mouse_button_down(MouseButton::MOUSE_BUTTON_MIDDLE)
+    {
+        state.panning = false;
     }
-    state.camera.zoom = state.camera.zoom.clamp(MIN_ZOOM, MAX_ZOOM);
 
-    if rl.is_mouse_button_down(MouseButton::MOUSE_BUTTON_RIGHT) {
+    // Releasing a pan lets the camera coast, slowing exponentially.
+    const PAN_FRICTION: f32 = 5.0;
+    const PAN_STOP_SPEED: f32 = 20.0;
+    let dt = rl.get_frame_time();
+    if state.panning {
         let delta = rl.get_mouse_delta() / state.camera.zoom;
         state.camera.target -= delta;
+        // Grabbing the canvas cancels any transition in flight.
+        state.camera_target_goal = state.camera.target;
+        if dt > 0.0 {
+            // Averaged over a few frames so one jittery frame doesn't fling the view.
+            state.pan_velocity = state.pan_velocity.lerp(delta / dt, 0.5);
+        }
+    } else if state.smooth_camera
+        && state.pan_velocity.length() * state.camera.zoom > PAN_STOP_SPEED
+    {
+        state.camera.target -= state.pan_velocity * dt;
+        state.camera_target_goal = state.camera.target;
+        state.pan_velocity *= (-PAN_FRICTION * dt).exp();
+    } else {
+        state.pan_velocity = Vector2::zero();
+    }
+
+    if state.panning || state.dragging.is_some() {
+        rl.set_mouse_cursor(MouseCursor::MOUSE_CURSOR_RESIZE_ALL);
+    } else if hovered.is_some() && !over_gui(state, mouse_pos) {
+        rl.set_mouse_cursor(MouseCursor::MOUSE_CURSOR_POINTING_HAND);
+    }
+
+    // Never let the tree leave the screen entirely. Hitting the limit stops any coasting.
+    let clamped = clamp_target_to_tree(
+        state.camera.target,
+        &state.camera,
+        screen_size,
+        layout_positions,
+    );
+    if clamped != state.camera.target {
+        state.pan_velocity = Vector2::zero();
+    }
+    state.camera.target = clamped;
+    state.camera_target_goal = clamp_target_to_tree(
+        state.camera_target_goal,
+        &state.camera,
+        screen_size,
+        layout_positions,
+    );
+
+    // A left click while editing belongs to the text box, which commits on it.
+    if editing {
+        return;
+    }
+
+    // --- Shift-drag to lasso ---
+    if shift_down && rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+        state.lasso = vec![world_mouse_pos];
+        return;
+    }
+    if let Some(last) = state.lasso.last() {
+        const MIN_POINT_SPACING: f32 = 4.0;
+        if rl.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT) {
+            if last.distance_to(world_mouse_pos) * state.camera.zoom > MIN_POINT_SPACING {
+                state.lasso.push(world_mouse_pos);
+            }
+        } else {
+            state.selected_ids = layout_positions
+                .iter()
+                .filter(|(_, pos)| point_in_polygon(**pos, &state.lasso))
+                .map(|(id, _)| *id)
+                .collect();
+            state.lasso.clear();
+        }
+        return;
+    }
+
+    // --- Alt-drag to zoom to a region ---
+    let alt_down =
+        rl.is_key_down(KeyboardKey::KEY_LEFT_ALT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_ALT);
+    if alt_down && rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+        state.zoom_region = Some(world_mouse_pos);
+        return;
+    }
+    if let Some(start) = state.zoom_region {
+        // Drags shorter than this on screen are taken as stray clicks.
+        const MIN_DRAG: f32 = 8.0;
+        if !rl.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT) {
+            state.zoom_region = None;
+            let region = region_rect(start, world_mouse_pos);
+            if region.width.max(region.height) * state.camera.zoom > MIN_DRAG {
+                state.camera_target_goal = Vector2::new(
+                    region.x + region.width / 2.0,
+                    region.y + region.height / 2.0,
+                );
+                state.camera_zoom_goal = (screen_size.x / region.width)
+                    .min(screen_size.y / region.height)
+                    .clamp(MIN_ZOOM, MAX_ZOOM);
+                state.zoom_anchor = None;
+            }
+        }
+        return;
+    }
+
+    // --- Free layout dragging ---
+    if let Some((id, grab_offset)) = state.dragging {
+        if rl.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT) {
+            if let Some(obj) = find_object_by_id_mut(&mut state.scene_objects, id) {
+                obj.position = Some(world_mouse_pos - grab_offset);
+                state.layout_dirty = true;
+            }
+        } else {
+            state.dragging = None;
+        }
+        return;
     }
 
     // --- Click to Select/Edit ---
     if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
-        let mouse_pos = rl.get_mouse_position();
-
-        if state.active_settings_id.is_some() && mouse_pos.x < 420.0 {
+        if over_gui(state, mouse_pos) {
             return;
         }
 
-        let world_mouse_pos = screen_to_world(mouse_pos, &state.camera);
-
-        let mut layout_positions = HashMap::new();
-        let mut start_y = 100.0;
-        for obj in &state.scene_objects {
-            layout_recursive(obj, 200.0, start_y, &mut start_y, &mut layout_positions);
+        // Clicking the minimap jumps there instead of selecting.
+        if let Some(minimap) = Minimap::new(screen_size, layout_positions)
+            && check_collision_point_rec(mouse_pos, minimap.rect)
+        {
+            state.camera_target_goal = minimap.to_world(mouse_pos);
+            return;
         }
 
-        let mut clicked_id = None;
-        for obj in &state.scene_objects {
-            if let Some(id) = find_clicked_object(obj, world_mouse_pos, &layout_positions) {
-                clicked_id = Some(id);
-                break;
+        state.selected_ids.clear();
+
+        // The first click recenters the camera, so the second one is matched by screen
+        // position rather than by hit-testing again.
+        const DOUBLE_CLICK_TIME: f64 = 0.3;
+        const DOUBLE_CLICK_DISTANCE: f32 = 6.0;
+        let now = rl.get_time();
+        if let Some((clicked_id, time, pos)) = state.last_click.take()
+            && now - time < DOUBLE_CLICK_TIME
+            && pos.distance_to(mouse_pos) < DOUBLE_CLICK_DISTANCE
+        {
+            match clicked_id {
+                Some(id) => {
+                    if let Some(obj) = find_object_by_id_mut(&mut state.scene_objects, id)
+                        && !obj.locked
+                    {
+                        obj.text_buffer = obj.text.clone();
+                        state.inline_edit_id = Some(id);
+                        state.active_textbox_id = Some(id);
+                    }
+                }
+                None => state.requests.push(EditorRequest::AddRoot),
             }
+            return;
         }
 
+        // Clicking a connector picks its parent. The tolerance is a few screen pixels.
+        const CONNECTOR_TOLERANCE: f32 = 5.0;
+        let shape_id = node_at(&state.scene_objects, world_mouse_pos, hit_positions);
+        let clicked_id = shape_id
+            .or_else(|| label_at(rl, &state.scene_objects, world_mouse_pos, hit_positions))
+            .or_else(|| {
+                connector_at(
+                    &state.scene_objects,
+                    world_mouse_pos,
+                    hit_positions,
+                    state.connector_style,
+                    state.layout_direction,
+                    CONNECTOR_TOLERANCE / state.camera.zoom,
+                )
+            });
+        state.last_click = Some((clicked_id, now, mouse_pos));
         if let Some(id) = clicked_id {
             state.active_settings_id = Some(id);
-            if let Some(obj_pos) = layout_positions.get(&id) {
-                let screen_width = rl.get_screen_width() as f32;
-                let viewport_center_x = (screen_width / 2.0 + screen_width) / 2.0;
-                let offset_x = (viewport_center_x - state.camera.offset.x) / state.camera.zoom;
-                state.camera.target = Vector2::new(obj_pos.x - offset_x, obj_pos.y);
+            // Free layout grabs the shape instead of recentering on it.
+            if state.free_layout {
+                if shape_id == Some(id)
+                    && let Some(obj_pos) = layout_positions.get(&id)
+                    && find_object_by_id(&state.scene_objects, id).is_some_and(|o| !o.locked)
+                {
+                    state.dragging = Some((id, world_mouse_pos - *obj_pos));
+                }
+            } else if let Some(obj_pos) = layout_positions.get(&id) {
+                state.camera_target_goal =
+                    panel_view_target(&state.camera, *obj_pos, screen_size.x);
+            }
+        }
+    }
+}
+
+/// Keyboard panning, bookmarks, dropped templates and the single-key and Ctrl
+/// shortcuts. Skipped while a text box has focus.
+fn process_shortcuts(rl: &mut RaylibHandle, state: &mut State) {
+    let shift_down =
+        rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+    let ctrl_down = rl.is_key_down(KeyboardKey::KEY_LEFT_CONTROL)
+        || rl.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL);
+
+    // --- Keyboard panning ---
+    // Arrow keys also pan, unless a node is selected and they may be reordering it.
+    const PAN_SPEED: f32 = 600.0;
+    let arrows = state.active_settings_id.is_none();
+    // With Ctrl held the letters are commands, like Ctrl+A, rather than panning.
+    let held =
+        |wasd, arrow| (!ctrl_down && rl.is_key_down(wasd)) || (arrows && rl.is_key_down(arrow));
+    let mut pan = Vector2::zero();
+    if held(KeyboardKey::KEY_W, KeyboardKey::KEY_UP) {
+        pan.y -= 1.0;
+    }
+    if held(KeyboardKey::KEY_S, KeyboardKey::KEY_DOWN) {
+        pan.y += 1.0;
+    }
+    if held(KeyboardKey::KEY_A, KeyboardKey::KEY_LEFT) {
+        pan.x -= 1.0;
+    }
+    if held(KeyboardKey::KEY_D, KeyboardKey::KEY_RIGHT) {
+        pan.x += 1.0;
+    }
+    if pan != Vector2::zero() {
+        // Screen-space speed, so it feels the same at every zoom level.
+        let step = PAN_SPEED * rl.get_frame_time() / state.camera.zoom;
+        state.camera.target += pan.normalized() * step;
+        state.camera_target_goal = state.camera.target;
+    }
+
+    // --- Bookmarks ---
+    const BOOKMARK_KEYS: [KeyboardKey; 9] = [
+        KeyboardKey::KEY_ONE,
+        KeyboardKey::KEY_TWO,
+        KeyboardKey::KEY_THREE,
+        KeyboardKey::KEY_FOUR,
+        KeyboardKey::KEY_FIVE,
+        KeyboardKey::KEY_SIX,
+        KeyboardKey::KEY_SEVEN,
+        KeyboardKey::KEY_EIGHT,
+        KeyboardKey::KEY_NINE,
+    ];
+    let alt_down =
+        rl.is_key_down(KeyboardKey::KEY_LEFT_ALT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_ALT);
+    for (slot, key) in BOOKMARK_KEYS.iter().enumerate() {
+        if !rl.is_key_pressed(*key) {
+            continue;
+        }
+        if alt_down {
+            // Alt+N shows only the top N levels.
+            state
+                .requests
+                .push(EditorRequest::CollapseToDepth { depth: slot + 1 });
+        } else if shift_down {
+            state.bookmarks[slot] = Some(Bookmark {
+                target: state.camera_target_goal,
+                zoom: state.camera_zoom_goal,
+                selected_id: state.active_settings_id,
+            });
+        } else if let Some(bookmark) = state.bookmarks[slot] {
+            // Eased like any other camera jump.
+            state.camera_target_goal = bookmark.target;
+            state.camera_zoom_goal = bookmark.zoom;
+            state.zoom_anchor = None;
+            state.active_settings_id = bookmark
+                .selected_id
+                .filter(|id| find_object_by_id(&state.scene_objects, *id).is_some());
+        }
+    }
+
+    // Dropping a template file onto the window inserts it under the selected node.
+    if rl.is_file_dropped() {
+        let dropped = rl.load_dropped_files();
+        for path in dropped.paths() {
+            match import_template(path) {
+                Ok(subtree) => state.requests.push(EditorRequest::Insert {
+                    parent_id: state.active_settings_id,
+                    subtree,
+                }),
+                Err(err) => println!("Couldn't import template '{}': {}", path, err),
+            }
+        }
+    }
+
+    if ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_S) {
+        match save_scene(&state.scene_objects, SCENE_PATH) {
+            Ok(()) => println!("Saved scene to '{}'", SCENE_PATH),
+            Err(err) => println!("Scene save failed: {}", err),
+        }
+    }
+    if ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_O) {
+        match load_scene(SCENE_PATH) {
+            Ok(objects) => state.requests.push(EditorRequest::ReplaceScene { objects }),
+            Err(err) => println!("Couldn't load scene '{}': {}", SCENE_PATH, err),
+        }
+    }
+
+    if rl.is_key_pressed(KeyboardKey::KEY_P) {
+        if shift_down {
+            match std::fs::write("tree.dot", export_dot(&state.scene_objects)) {
+                Ok(()) => println!("Exported tree structure to 'tree.dot'"),
+                Err(err) => println!("DOT export failed: {}", err),
             }
+        } else if ctrl_down {
+            // The selected subtree, or the whole tree with nothing selected.
+            let selected = state
+                .active_settings_id
+                .and_then(|id| find_object_by_id(&state.scene_objects, id));
+            let objects = match selected {
+                Some(obj) => std::slice::from_ref(obj),
+                None => &state.scene_objects[..],
+            };
+            match std::fs::write(
+                "tree.svg",
+                export_svg(objects, &state.layout_positions, state.theme),
+            ) {
+                Ok(()) => println!("Exported tree drawing to 'tree.svg'"),
+                Err(err) => println!("SVG export failed: {}", err),
+            }
+        } else {
+            state.export_requested = true;
+        }
+    }
+    if !ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_I) {
+        state.show_stats = !state.show_stats;
+    }
+    if !ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_N) {
+        state.show_ids = !state.show_ids;
+    }
+    if rl.is_key_pressed(KeyboardKey::KEY_L) {
+        state.label_plates = !state.label_plates;
+    }
+    if rl.is_key_pressed(KeyboardKey::KEY_K) {
+        state.labels_rotate = !state.labels_rotate;
+    }
+    if !ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_G) {
+        state.show_grid = !state.show_grid;
+    }
+    if rl.is_key_pressed(KeyboardKey::KEY_T) {
+        state.theme = state.theme.next();
+    }
+    if rl.is_key_pressed(KeyboardKey::KEY_R) {
+        state.inherit_rotation = !state.inherit_rotation;
+        // Turning it off puts every subtree back where the layout has it.
+        state.layout_dirty = true;
+    }
+    if rl.is_key_pressed(KeyboardKey::KEY_GRAVE) {
+        state.show_perf = !state.show_perf;
+    }
+    if shift_down && rl.is_key_pressed(KeyboardKey::KEY_B) {
+        benchmark_layout(state, 10_000);
+    }
+    if rl.is_key_pressed(KeyboardKey::KEY_LEFT_BRACKET) {
+        state.time_scale = (state.time_scale / 2.0).max(MIN_TIME_SCALE);
+    }
+    if rl.is_key_pressed(KeyboardKey::KEY_RIGHT_BRACKET) {
+        state.time_scale = (state.time_scale * 2.0).min(MAX_TIME_SCALE);
+    }
+    if rl.is_key_pressed(KeyboardKey::KEY_M) {
+        state.smooth_camera = !state.smooth_camera;
+    }
+    if shift_down && !ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_F) {
+        // Frames the hovered node and its children, eased like any other camera jump.
+        let screen_size = Vector2::new(rl.get_screen_width() as f32, rl.get_screen_height() as f32);
+        if let Some(id) = state.hover_id
+            && let Some((target, zoom)) = frame_node(state, id, screen_size)
+        {
+            state.camera_target_goal = target;
+            state.camera_zoom_goal = zoom;
+            state.zoom_anchor = None;
+        }
+    } else if !ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_F) {
+        state.free_layout = !state.free_layout;
+        state.dragging = None;
+    }
+    // Text boxes swallow input above, so this can't fire while renaming.
+    if rl.is_key_pressed(KeyboardKey::KEY_DELETE) {
+        let mut node_ids: Vec<u32> = state.active_settings_id.into_iter().collect();
+        node_ids.extend(
+            state
+                .selected_ids
+                .iter()
+                .filter(|id| state.active_settings_id != Some(**id)),
+        );
+        if !node_ids.is_empty() {
+            state.requests.push(EditorRequest::DeleteNodes { node_ids });
+        }
+    }
+
+    if let Some(parent_id) = state.active_settings_id
+        && (rl.is_key_pressed(KeyboardKey::KEY_INSERT)
+            || (ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_N)))
+    {
+        state.requests.push(EditorRequest::AddChild {
+            parent_id,
+            select: true,
+        });
+    }
+    if ctrl_down
+        && rl.is_key_pressed(KeyboardKey::KEY_C)
+        && let Some(id) = state.active_settings_id
+        && let Some(obj) = find_object_by_id(&state.scene_objects, id)
+    {
+        state.clipboard = Some(obj.clone());
+    }
+    // Ctrl+A selects every node and Ctrl+I flips the selection. Only a single selected
+    // node gets the settings panel.
+    let select_all = ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_A);
+    let invert = ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_I);
+    if select_all || invert {
+        let everything = all_ids(&state.scene_objects).into_iter();
+        state.selected_ids = if select_all {
+            everything.collect()
+        } else {
+            everything
+                .filter(|id| !state.selected_ids.contains(id))
+                .collect()
+        };
+        state.active_settings_id = match state.selected_ids.len() {
+            1 => state.selected_ids.iter().next().copied(),
+            _ => None,
+        };
+    }
+    // Ctrl+G groups the lasso selection, or the selected node when nothing is lassoed.
+    if ctrl_down && !shift_down && rl.is_key_pressed(KeyboardKey::KEY_G) {
+        let node_ids: Vec<u32> = if state.selected_ids.is_empty() {
+            state.active_settings_id.into_iter().collect()
+        } else {
+            state.selected_ids.iter().copied().collect()
+        };
+        state.requests.push(EditorRequest::Group { node_ids });
+    }
+    if ctrl_down
+        && shift_down
+        && rl.is_key_pressed(KeyboardKey::KEY_G)
+        && let Some(node_id) = state.active_settings_id
+    {
+        state.requests.push(EditorRequest::Ungroup { node_id });
+    }
+    if ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_F) {
+        state.search_focused = true;
+    }
+    // Ctrl+Tab moves the settings panel's keyboard focus instead.
+    if !ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_TAB) && state.active_settings_id.is_some() {
+        state.quick_add = Some(String::new());
+    }
+    if ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_V) && state.clipboard.is_some() {
+        state.requests.push(EditorRequest::Paste {
+            parent_id: state.active_settings_id,
+        });
+    }
+    if ctrl_down && let Some(id) = state.active_settings_id {
+        if rl.is_key_pressed(KeyboardKey::KEY_UP) {
+            state.requests.push(EditorRequest::MoveSibling {
+                node_id: id,
+                delta: -1,
+            });
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_DOWN) {
+            state.requests.push(EditorRequest::MoveSibling {
+                node_id: id,
+                delta: 1,
+            });
+        }
+    }
+    // Tree-wide changes sit behind Shift, so a slip off the WASD keys can't fire them.
+    let shift_only = shift_down && !ctrl_down;
+    if shift_only && rl.is_key_pressed(KeyboardKey::KEY_C) {
+        state
+            .requests
+            .push(EditorRequest::SetCollapsedAll { collapsed: true });
+    }
+    if shift_only && rl.is_key_pressed(KeyboardKey::KEY_E) {
+        state
+            .requests
+            .push(EditorRequest::SetCollapsedAll { collapsed: false });
+    }
+    if shift_only && rl.is_key_pressed(KeyboardKey::KEY_H) {
+        state
+            .requests
+            .push(EditorRequest::Align { horizontal: true });
+    }
+    if shift_only && rl.is_key_pressed(KeyboardKey::KEY_V) {
+        state
+            .requests
+            .push(EditorRequest::Align { horizontal: false });
+    }
+}
+
+/// Eases the camera towards its goal target and zoom, and recentres it if the window
+/// has changed to `screen_size`. Runs once per rendered frame.
+pub fn update_camera(state: &mut State, dt: f32, screen_size: Vector2) {
+    state.set_screen_size(screen_size);
+    // Covers ~98% of the distance in about a quarter of a second.
+    const CAMERA_SMOOTHING: f32 = 16.0;
+    const SNAP_DISTANCE: f32 = 0.01;
+
+    let t = if state.smooth_camera {
+        (CAMERA_SMOOTHING * dt).min(1.0)
+    } else {
+        1.0
+    };
+    let camera = &mut state.camera;
+
+    let zoom_before = camera.zoom;
+    camera.zoom += (state.camera_zoom_goal - camera.zoom) * t;
+    if (camera.zoom - state.camera_zoom_goal).abs() < SNAP_DISTANCE * 0.1 {
+        camera.zoom = state.camera_zoom_goal;
+    }
+    if let Some(anchor) = state.zoom_anchor {
+        // Shift both target and goal so the world point under the anchor stays put.
+        let shift = (anchor - camera.offset) * (1.0 / zoom_before - 1.0 / camera.zoom);
+        camera.target += shift;
+        state.camera_target_goal += shift;
+        if camera.zoom == state.camera_zoom_goal {
+            state.zoom_anchor = None;
         }
     }
+
+    camera.target = camera.target.lerp(state.camera_target_goal, t);
+    if camera.target.distance_to(state.camera_target_goal) < SNAP_DISTANCE {
+        camera.target = state.camera_target_goal;
+    }
 }
 
 /// Updates the state of all objects in the scene.
@@ -169,42 +1382,124 @@ pub fn step(state: &mut State, dt: f32) {
         return;
     }
 
+    let mut animating = false;
     for obj in &mut state.scene_objects {
-        update_object_recursively(obj, dt);
+        animating |= update_object_recursively(obj, dt, state.inherit_rotation, 0.0);
+    }
+    // Collapsing children keep their layout slots until they have slid all the way in.
+    if animating {
+        state.layout_dirty = true;
     }
     process_editor_requests(state);
 }
 
-fn update_object_recursively(obj: &mut SceneObject, dt: f32) {
+/// Advances the spin and collapse animation of `obj` and its subtree. With `inherit` on,
+/// each node also takes on `parent_angle`, the drawn angle of its parent. True if any
+/// node's `expand_progress` moved.
+fn update_object_recursively(
+    obj: &mut SceneObject,
+    dt: f32,
+    inherit: bool,
+    parent_angle: f32,
+) -> bool {
     obj.current_rotation += obj.rotation_speed * dt;
+    obj.inherited_rotation = if inherit { parent_angle } else { 0.0 };
+    let target = if obj.collapsed { 0.0 } else { 1.0 };
+    let mut animating = obj.expand_progress != target;
+    let step = dt / COLLAPSE_ANIM_SECS;
+    obj.expand_progress = if obj.collapsed {
+        (obj.expand_progress - step).max(0.0)
+    } else {
+        (obj.expand_progress + step).min(1.0)
+    };
+    let angle = obj.angle();
     for child in &mut obj.children {
-        update_object_recursively(child, dt);
+        animating |= update_object_recursively(child, dt, inherit, angle);
     }
+    animating
 }
 
 /// Main drawing function.
 pub fn draw(state: &mut State, d: &mut RaylibDrawHandle) {
-    let mut layout_positions = HashMap::new();
-    let mut start_y = 100.0;
-    for root_obj in &state.scene_objects {
-        layout_recursive(
-            root_obj,
-            200.0,
-            start_y,
-            &mut start_y,
-            &mut layout_positions,
-        );
+    refresh_layout(state);
+    // Taken for the frame so the widgets below can borrow `state` mutably.
+    let layout_positions = std::mem::take(&mut state.layout_positions);
+
+    // The confirmation dialog and help overlay are modal, so every other control is
+    // inert under them.
+    if state.pending_confirm.is_some() || state.show_help {
+        d.gui_lock();
     }
+    // Each widget below adds the area it covers.
+    state.gui_rects.clear();
 
     // --- Draw all world objects ---
+    let font = d.get_font_default();
     {
+        let screen_size = Vector2::new(d.get_screen_width() as f32, d.get_screen_height() as f32);
+        // Mid-animation children are drawn between their parent and their layout spot.
+        let sliding_positions = (!state.collapse_fades.is_empty()).then(|| {
+            let mut positions = HashMap::new();
+            slide_positions(
+                &state.scene_objects,
+                None,
+                &layout_positions,
+                &mut positions,
+            );
+            positions
+        });
+        let view = WorldView {
+            positions: sliding_positions.as_ref().unwrap_or(&layout_positions),
+            connector_style: state.connector_style,
+            layout_direction: state.layout_direction,
+            connector_thickness: state.connector_thickness,
+            subtree_sizes: state
+                .weight_connectors
+                .then(|| subtree_sizes(&state.scene_objects)),
+            color_by_depth: state.color_by_depth,
+            show_ids: state.show_ids,
+            label_plates: state.label_plates,
+            labels_rotate: state.labels_rotate,
+            highlighted_path: state
+                .active_settings_id
+                .and_then(|id| path_to_node(&state.scene_objects, id))
+                .unwrap_or_default(),
+            selected_ids: &state.selected_ids,
+            search_matches: &state.search_matches,
+            active_id: state.active_settings_id,
+            time: d.get_time() as f32,
+            visible_rect: Some(visible_world_rect(&state.camera, screen_size)),
+            filter_kept: state
+                .filter_category
+                .as_deref()
+                .map(|category| category_filter_ids(&state.scene_objects, category)),
+            fades: &state.collapse_fades,
+            theme: state.theme,
+        };
         let mut d2d = d.begin_mode2D(state.camera);
-        for (id, pos) in &layout_positions {
-            if let Some(obj) = find_object_by_id_mut(&mut state.scene_objects, *id) {
-                draw_world_object(&mut d2d, obj, *pos, &layout_positions);
-            }
+        if state.show_grid {
+            draw_grid(&mut d2d, &state.camera, screen_size, state.theme.text);
         }
+        draw_world(&mut d2d, &font, &state.scene_objects, &view);
+        draw_lasso(&mut d2d, &state.lasso, state.camera.zoom);
+        if let Some(start) = state.zoom_region {
+            let corner = screen_to_world(d2d.get_mouse_position(), &state.camera);
+            draw_zoom_region(&mut d2d, region_rect(start, corner), state.camera.zoom);
+        }
+    }
+
+    draw_minimap(d, &state.camera, &layout_positions);
+    draw_view_toolbar(d, state);
+    if state.search_focused || !state.search_query.is_empty() {
+        draw_search_bar(d, state);
+    }
+    if state.quick_add.is_some() {
+        draw_quick_add(d, state);
     }
+    if state.show_stats {
+        draw_stats(d, state);
+    }
+    draw_context_menu(d, state);
 
     // --- Draw the ONE active settings panel on top of everything else ---
     let State {
@@ -212,239 +1507,3250 @@ pub fn draw(state: &mut State, d: &mut RaylibDrawHandle) {
         active_settings_id,
         requests,
         active_textbox_id,
+        panel_field,
+        panel,
+        color_by_depth,
+        filter_category,
+        gui_rects,
         ..
     } = state;
 
-    if let Some(id) = *active_settings_id {
-        if let Some(obj) = find_object_by_id_mut(scene_objects, id) {
-            draw_settings_panel(d, active_settings_id, requests, active_textbox_id, obj);
+    if let Some(id) = *active_settings_id
+        && let Some(path) = path_to_node(scene_objects, id)
+        && let Some(obj) = find_object_by_id_mut(scene_objects, id)
+    {
+        let context = PanelContext {
+            color_by_depth: *color_by_depth,
+            depth: path.len(),
+            filter_category: filter_category.as_deref(),
+            panel: *panel,
+            background: state.theme.background,
+            typing_elsewhere: state.search_focused || state.quick_add.is_some(),
+        };
+        let screen_size = Vector2::new(d.get_screen_width() as f32, d.get_screen_height() as f32);
+        gui_rects.push(settings_panel_rect(screen_size, panel.pos));
+        *panel = draw_settings_panel(
+            d,
+            active_settings_id,
+            requests,
+            active_textbox_id,
+            panel_field,
+            &context,
+            obj,
+        );
+    } else {
+        // A focus left over from a closed panel must not carry into the next one.
+        panel.focus_index = None;
+    }
+
+    if let Some(id) = state.inline_edit_id {
+        draw_inline_editor(d, state, id, &layout_positions, &font);
+    }
+
+    if let Some((text, since)) = &state.notice {
+        const NOTICE_SECS: f32 = 3.0;
+        if since.elapsed().as_secs_f32() < NOTICE_SECS {
+            let width = d.measure_text(text, 20);
+            d.draw_text(
+                text,
+                (d.get_screen_width() - width) / 2,
+                d.get_screen_height() - 40,
+                20,
+                state.theme.text,
+            );
+        } else {
+            state.notice = None;
         }
     }
 
-    d.draw_text(
-        "Click a shape to edit. Right Mouse to Pan, Scroll Wheel to Zoom.",
-        10,
-        10,
-        20,
-        Color::WHITE,
-    );
-}
+    if state.scene_objects.is_empty() {
+        let hint = "Empty scene - double-click to add a node";
+        let width = d.measure_text(hint, 20);
+        d.draw_text(
+            hint,
+            (d.get_screen_width() - width) / 2,
+            d.get_screen_height() / 2 - 10,
+            20,
+            state.theme.muted_text,
+        );
+    }
 
-/// The layout algorithm. Calculates positions for a left-to-right tree.
-fn layout_recursive(
-    obj: &SceneObject,
-    x: f32,
-    y_start: f32,
-    y_cursor: &mut f32,
+    let help = if state.free_layout {
+        "Free layout: drag nodes to move them, F to leave. F1 for help."
+    } else {
+        "Click a shape to edit. Right/Middle Mouse to Pan, Scroll Wheel to Zoom. F1 for help."
+    };
+    d.draw_text(help, 10, 10, 20, state.theme.text);
+
+    let screen_size = Vector2::new(d.get_screen_width() as f32, d.get_screen_height() as f32);
+    if let Some(category) = &state.filter_category {
+        let bounds = filter_banner_rect(screen_size);
+        state.gui_rects.push(bounds);
+        if d.gui_button(bounds, &format!("Clear filter: {}", category)) {
+            state.filter_category = None;
+        }
+    }
+
+    if state.show_perf {
+        draw_perf_overlay(d, state);
+    }
+    draw_zoom_readout(d, state);
+
+    draw_tooltip(d, state);
+
+    if state.show_help {
+        d.gui_unlock();
+        draw_help_overlay(d, state);
+    }
+
+    if state.pending_confirm.is_some() {
+        d.gui_unlock();
+        draw_confirm_dialog(d, state);
+    }
+
+    state.layout_positions = layout_positions;
+}
+
+/// A scaled-down overview of the tree, anchored to the bottom-right of the screen.
+struct Minimap {
+    rect: Rectangle,
+    bounds: Rectangle,
+    scale: f32,
+}
+
+impl Minimap {
+    const WIDTH: f32 = 200.0;
+    const HEIGHT: f32 = 150.0;
+    const MARGIN: f32 = 10.0;
+
+    fn new(screen_size: Vector2, positions: &HashMap<u32, Vector2>) -> Option<Self> {
+        let bounds = tree_bounds(positions)?;
+        let rect = Rectangle::new(
+            screen_size.x - Self::WIDTH - Self::MARGIN,
+            screen_size.y - Self::HEIGHT - Self::MARGIN,
+            Self::WIDTH,
+            Self::HEIGHT,
+        );
+        let scale = (rect.width / bounds.width).min(rect.height / bounds.height);
+        Some(Self {
+            rect,
+            bounds,
+            scale,
+        })
+    }
+
+    /// Screen position of the minimap's centre; the tree is centred on it.
+    fn center(&self) -> Vector2 {
+        Vector2::new(
+            self.rect.x + self.rect.width / 2.0,
+            self.rect.y + self.rect.height / 2.0,
+        )
+    }
+
+    fn bounds_center(&self) -> Vector2 {
+        Vector2::new(
+            self.bounds.x + self.bounds.width / 2.0,
+            self.bounds.y + self.bounds.height / 2.0,
+        )
+    }
+
+    fn to_screen(&self, world_pos: Vector2) -> Vector2 {
+        (world_pos - self.bounds_center()) * self.scale + self.center()
+    }
+
+    fn to_world(&self, screen_pos: Vector2) -> Vector2 {
+        (screen_pos - self.center()) / self.scale + self.bounds_center()
+    }
+}
+
+/// Draws the minimap: one dot per node plus an outline of the camera's view.
+fn draw_minimap(d: &mut RaylibDrawHandle, camera: &Camera2D, positions: &HashMap<u32, Vector2>) {
+    let screen_size = Vector2::new(d.get_screen_width() as f32, d.get_screen_height() as f32);
+    let Some(minimap) = Minimap::new(screen_size, positions) else {
+        return;
+    };
+
+    d.draw_rectangle_rec(minimap.rect, Color::BLACK.alpha(0.6));
+    d.draw_rectangle_lines_ex(minimap.rect, 1.0, Color::GRAY);
+
+    let view_min = minimap.to_screen(screen_to_world(Vector2::new(0.0, 0.0), camera));
+    let view_max = minimap.to_screen(screen_to_world(screen_size, camera));
+
+    // Clip so a zoomed-out viewport outline doesn't spill past the minimap.
+    let mut clipped = d.begin_scissor_mode(
+        minimap.rect.x as i32,
+        minimap.rect.y as i32,
+        minimap.rect.width as i32,
+        minimap.rect.height as i32,
+    );
+    for pos in positions.values() {
+        clipped.draw_circle_v(minimap.to_screen(*pos), 2.0, Color::WHITE);
+    }
+    clipped.draw_rectangle_lines_ex(
+        Rectangle::new(
+            view_min.x,
+            view_min.y,
+            view_max.x - view_min.x,
+            view_max.y - view_min.y,
+        ),
+        1.0,
+        Color::YELLOW,
+    );
+}
+
+/// Renders the whole tree into an offscreen texture and saves it as a PNG.
+/// The texture is sized to the tree bounds, so nodes outside the current view are included.
+pub fn export_png(rl: &mut RaylibHandle, thread: &RaylibThread, state: &State, path: &str) {
+    const EXPORT_SCALE: f32 = 2.0;
+    const MAX_TEXTURE_SIZE: f32 = 8192.0;
+
+    let layout_positions = compute_layout(state);
+    let Some(bounds) = tree_bounds(&layout_positions) else {
+        return;
+    };
+
+    // Shrink the scale rather than exceed what the GPU can allocate.
+    let largest_side = bounds.width.max(bounds.height) * EXPORT_SCALE;
+    let scale = if largest_side > MAX_TEXTURE_SIZE {
+        EXPORT_SCALE * MAX_TEXTURE_SIZE / largest_side
+    } else {
+        EXPORT_SCALE
+    };
+    let width = (bounds.width * scale).ceil() as u32;
+    let height = (bounds.height * scale).ceil() as u32;
+
+    let mut target = match rl.load_render_texture(thread, width, height) {
+        Ok(target) => target,
+        Err(err) => {
+            println!("Export failed: {}", err);
+            return;
+        }
+    };
+    let camera = Camera2D {
+        target: Vector2::new(
+            bounds.x + bounds.width / 2.0,
+            bounds.y + bounds.height / 2.0,
+        ),
+        offset: Vector2::new(width as f32 / 2.0, height as f32 / 2.0),
+        rotation: 0.0,
+        zoom: scale,
+    };
+
+    let font = rl.get_font_default();
+    {
+        let mut d_texture = rl.begin_texture_mode(thread, &mut target);
+        d_texture.clear_background(state.theme.background);
+        let mut d2d = d_texture.begin_mode2D(camera);
+        // The export leaves out the selection highlights.
+        let no_selection = HashSet::new();
+        let no_fades = HashMap::new();
+        let view = WorldView {
+            positions: &layout_positions,
+            connector_style: state.connector_style,
+            layout_direction: state.layout_direction,
+            connector_thickness: state.connector_thickness,
+            subtree_sizes: state
+                .weight_connectors
+                .then(|| subtree_sizes(&state.scene_objects)),
+            color_by_depth: state.color_by_depth,
+            show_ids: state.show_ids,
+            label_plates: state.label_plates,
+            labels_rotate: state.labels_rotate,
+            highlighted_path: Vec::new(),
+            selected_ids: &no_selection,
+            search_matches: &no_selection,
+            active_id: None,
+            time: 0.0,
+            visible_rect: None,
+            filter_kept: state
+                .filter_category
+                .as_deref()
+                .map(|category| category_filter_ids(&state.scene_objects, category)),
+            fades: &no_fades,
+            theme: state.theme,
+        };
+        draw_world(&mut d2d, &font, &state.scene_objects, &view);
+    }
+
+    match target.load_image() {
+        Ok(mut image) => {
+            // Render textures are stored bottom-up.
+            image.flip_vertical();
+            image.export_image(path);
+            println!("Exported {}x{} tree image to '{}'", width, height, path);
+        }
+        Err(err) => println!("Export failed: {}", err),
+    }
+}
+
+/// Saves `obj` and its subtree as a template file.
+fn export_template(obj: &SceneObject, path: &str) -> std::io::Result<()> {
+    std::fs::write(path, scene_object_to_json(obj).to_pretty_string())
+}
+
+/// Loads a subtree saved by `export_template`. Ids a hand-edited file repeats are
+/// replaced here; inserting it through `EditorRequest::Insert` then hands out fresh
+/// ones for the whole subtree.
+fn import_template(path: &str) -> Result<SceneObject, String> {
+    let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let mut obj = scene_object_from_json(&json::parse(&text)?)?;
+    let fixed = obj.sanitize();
+    if fixed > 0 {
+        println!("Fixed {} out-of-range values in '{}'", fixed, path);
+    }
+    report_reassigned_ids(std::slice::from_mut(&mut obj), path);
+    Ok(obj)
+}
+
+/// Saves every root and its subtree, with each node's full settings, for `load_scene`.
+fn save_scene(objects: &[SceneObject], path: &str) -> std::io::Result<()> {
+    let json = Json::Object(vec![(
+        "nodes".to_string(),
+        Json::Array(objects.iter().map(scene_object_to_json).collect()),
+    )]);
+    std::fs::write(path, json.to_pretty_string())
+}
+
+/// Loads a tree saved by `save_scene`, with out-of-range values pulled back in.
+fn load_scene(path: &str) -> Result<Vec<SceneObject>, String> {
+    let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let value = json::parse(&text)?;
+    let mut objects = value
+        .get("nodes")
+        .and_then(Json::as_array)
+        .ok_or("missing nodes")?
+        .iter()
+        .map(scene_object_from_json)
+        .collect::<Result<Vec<_>, _>>()?;
+    let fixed: usize = objects.iter_mut().map(SceneObject::sanitize).sum();
+    if fixed > 0 {
+        println!("Fixed {} out-of-range values in '{}'", fixed, path);
+    }
+    report_reassigned_ids(&mut objects, path);
+    Ok(objects)
+}
+
+/// Runs `reassign_duplicate_ids` on a tree loaded from `path` and says if it changed any.
+fn report_reassigned_ids(objects: &mut [SceneObject], path: &str) {
+    let changed = reassign_duplicate_ids(objects);
+    if changed > 0 {
+        println!(
+            "'{}' repeats {} ids; those nodes got new ones",
+            path, changed
+        );
+    }
+}
+
+/// The whole tree as a Graphviz digraph: one `id [label="text"]` line per node, then
+/// one edge per parent-child pair. Collapsed and hidden nodes are included.
+fn export_dot(objects: &[SceneObject]) -> String {
+    fn write_node(out: &mut String, obj: &SceneObject) {
+        let mut label = String::new();
+        for c in obj.text.chars() {
+            match c {
+                '"' => label.push_str("\\\""),
+                '\\' => label.push_str("\\\\"),
+                '\n' => label.push_str("\\n"),
+                c => label.push(c),
+            }
+        }
+        out.push_str(&format!("    {} [label=\"{}\"];\n", obj.id, label));
+        for child in &obj.children {
+            out.push_str(&format!("    {} -> {};\n", obj.id, child.id));
+            write_node(out, child);
+        }
+    }
+    let mut out = String::from("digraph tree {\n");
+    for obj in objects {
+        write_node(&mut out, obj);
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// `objects` and their subtrees as an SVG drawing at `positions`, in `theme`'s colours
+/// like the PNG export: connectors as lines, then shapes with their rotation, then names.
+/// Nodes without a position (inside a collapsed subtree) are left out, as they are on
+/// screen.
+fn export_svg(objects: &[SceneObject], positions: &HashMap<u32, Vector2>, theme: Theme) -> String {
+    fn collect<'a>(
+        obj: &'a SceneObject,
+        positions: &HashMap<u32, Vector2>,
+        out: &mut Vec<(&'a SceneObject, Vector2)>,
+    ) {
+        let Some(pos) = positions.get(&obj.id) else {
+            return;
+        };
+        out.push((obj, *pos));
+        for child in &obj.children {
+            collect(child, positions, out);
+        }
+    }
+    let mut placed = Vec::new();
+    for obj in objects {
+        collect(obj, positions, &mut placed);
+    }
+    let placed_positions: HashMap<u32, Vector2> =
+        placed.iter().map(|(obj, pos)| (obj.id, *pos)).collect();
+    let bounds = tree_bounds(&placed_positions).unwrap_or(Rectangle::new(0.0, 0.0, 1.0, 1.0));
+
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{:.1} {:.1} {:.1} {:.1}\">\n",
+        bounds.x, bounds.y, bounds.width, bounds.height
+    );
+    out.push_str(&format!(
+        "  <rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"/>\n",
+        bounds.x,
+        bounds.y,
+        bounds.width,
+        bounds.height,
+        svg_color(theme.background)
+    ));
+    for (obj, pos) in &placed {
+        for child in &obj.children {
+            if let Some(child_pos) = placed_positions.get(&child.id) {
+                out.push_str(&format!(
+                    "  <line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"{}\" stroke-opacity=\"{:.3}\"/>\n",
+                    pos.x,
+                    pos.y,
+                    child_pos.x,
+                    child_pos.y,
+                    svg_color(theme.connector),
+                    child.alpha as f32 / 255.0
+                ));
+            }
+        }
+    }
+    for (obj, pos) in &placed {
+        out.push_str("  ");
+        out.push_str(&svg_shape(obj, *pos));
+        out.push('\n');
+    }
+    for (obj, pos) in &placed {
+        if obj.visible && !obj.text.is_empty() {
+            let label = label_rect(*pos, obj.size(), 0.0);
+            out.push_str(&format!(
+                "  <text x=\"{:.1}\" y=\"{:.1}\" font-family=\"sans-serif\" font-size=\"{}\" text-anchor=\"middle\" dominant-baseline=\"hanging\" fill=\"{}\" fill-opacity=\"{:.3}\">{}</text>\n",
+                label.x,
+                label.y,
+                LABEL_FONT_SIZE,
+                svg_color(theme.text),
+                obj.alpha as f32 / 255.0,
+                xml_escape(&obj.text)
+            ));
+        }
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+/// One SVG element for a node's shape, filled with its colour and stroked with its
+/// outline. Hidden nodes get the dashed ring they show on screen.
+fn svg_shape(obj: &SceneObject, pos: Vector2) -> String {
+    let size = obj.size();
+    let angle = obj.angle();
+    if !obj.visible {
+        return format!(
+            "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{:.1}\" fill=\"none\" stroke=\"gray\" stroke-dasharray=\"4 4\"/>",
+            pos.x,
+            pos.y,
+            size / 2.0
+        );
+    }
+    let mut paint = format!(
+        "fill=\"{}\" fill-opacity=\"{:.3}\"",
+        svg_color(obj.color),
+        obj.alpha as f32 / 255.0
+    );
+    if let Some(outline) = obj.outline {
+        paint.push_str(&format!(
+            " stroke=\"{}\" stroke-width=\"{:.1}\"",
+            svg_color(outline),
+            obj.outline_thickness
+        ));
+    }
+    let polygon = |points: &[Vector2]| {
+        let points: Vec<String> = points
+            .iter()
+            .map(|p| format!("{:.1},{:.1}", p.x, p.y))
+            .collect();
+        format!("<polygon points=\"{}\" {}/>", points.join(" "), paint)
+    };
+    if let Some(glyph) = obj.glyph {
+        return format!(
+            "<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"{:.1}\" text-anchor=\"middle\" dominant-baseline=\"central\" transform=\"rotate({:.1} {:.1} {:.1})\" {}>{}</text>",
+            pos.x,
+            pos.y,
+            size,
+            angle,
+            pos.x,
+            pos.y,
+            paint,
+            xml_escape(&glyph.to_string())
+        );
+    }
+    match obj.shape {
+        Shape::Square => format!(
+            "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" transform=\"rotate({:.1} {:.1} {:.1})\" {}/>",
+            pos.x - size / 2.0,
+            pos.y - size / 2.0,
+            size,
+            size,
+            angle,
+            pos.x,
+            pos.y,
+            paint
+        ),
+        Shape::Circle => format!(
+            "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{:.1}\" {}/>",
+            pos.x,
+            pos.y,
+            size / 2.0,
+            paint
+        ),
+        Shape::Triangle => polygon(&triangle_vertices(pos, size, angle)),
+        Shape::Star => polygon(&star_vertices(pos, size, angle)),
+        // Corners where raylib's `draw_poly` puts them, the first at `angle`.
+        Shape::Hexagon => polygon(&std::array::from_fn::<_, 6, _>(|i| {
+            let (sin_a, cos_a) = (angle + i as f32 * 60.0).to_radians().sin_cos();
+            pos + Vector2::new(cos_a, sin_a) * (size / 2.0)
+        })),
+    }
+}
+
+fn svg_color(color: Color) -> String {
+    format!("rgb({},{},{})", color.r, color.g, color.b)
+}
+
+fn xml_escape(text: &str) -> String {
+    let mut out = String::new();
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Writes `session` to `path` as JSON.
+pub fn save_session(session: &Session, path: &str) -> std::io::Result<()> {
+    let json = Json::Object(vec![
+        (
+            "window_width".to_string(),
+            Json::Number(session.window_width as f64),
+        ),
+        (
+            "window_height".to_string(),
+            Json::Number(session.window_height as f64),
+        ),
+        (
+            "camera_target".to_string(),
+            Json::Array(vec![
+                Json::Number(session.camera_target.x as f64),
+                Json::Number(session.camera_target.y as f64),
+            ]),
+        ),
+        (
+            "camera_zoom".to_string(),
+            Json::Number(session.camera_zoom as f64),
+        ),
+        (
+            "smooth_camera".to_string(),
+            Json::Bool(session.smooth_camera),
+        ),
+    ]);
+    std::fs::write(path, json.to_pretty_string())
+}
+
+/// Reads a session saved by `save_session`. A missing file is the normal first run and
+/// returns None quietly; anything unreadable or out of range is reported and ignored.
+pub fn load_session(path: &str) -> Option<Session> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return None,
+        Err(err) => {
+            println!("Couldn't read session '{}': {}", path, err);
+            return None;
+        }
+    };
+    match parse_session(&text) {
+        Ok(session) => Some(session),
+        Err(err) => {
+            println!("Ignoring session '{}': {}", path, err);
+            None
+        }
+    }
+}
+
+fn parse_session(text: &str) -> Result<Session, String> {
+    let value = json::parse(text)?;
+    let number = |key| {
+        value
+            .get(key)
+            .and_then(Json::as_f64)
+            .filter(|n| n.is_finite())
+            .ok_or(format!("missing or invalid {}", key))
+    };
+    let width = number("window_width")?;
+    let height = number("window_height")?;
+    if !(200.0..=16384.0).contains(&width) || !(200.0..=16384.0).contains(&height) {
+        return Err("window size out of range".to_string());
+    }
+    let Some([x, y]) = value.get("camera_target").and_then(Json::as_array) else {
+        return Err("missing or invalid camera_target".to_string());
+    };
+    let (Some(x), Some(y)) = (x.as_f64(), y.as_f64()) else {
+        return Err("missing or invalid camera_target".to_string());
+    };
+    if !x.is_finite() || !y.is_finite() {
+        return Err("missing or invalid camera_target".to_string());
+    }
+    let zoom = number("camera_zoom")?;
+    if !(MIN_ZOOM as f64..=MAX_ZOOM as f64).contains(&zoom) {
+        return Err("camera_zoom out of range".to_string());
+    }
+    Ok(Session {
+        window_width: width as i32,
+        window_height: height as i32,
+        camera_target: Vector2::new(x as f32, y as f32),
+        camera_zoom: zoom as f32,
+        // Added later; older files keep the default.
+        smooth_camera: value
+            .get("smooth_camera")
+            .and_then(Json::as_bool)
+            .unwrap_or(true),
+    })
+}
+
+/// A file name in the working directory for a template named after a node.
+fn template_path(name: &str) -> String {
+    let stem: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}.template.json", stem)
+}
+
+/// Serializes a node and its subtree. Animation state like `current_rotation` is left out.
+fn scene_object_to_json(obj: &SceneObject) -> Json {
+    let color_json = |c: Color| {
+        Json::Array(
+            [c.r, c.g, c.b, c.a]
+                .iter()
+                .map(|v| Json::Number(*v as f64))
+                .collect(),
+        )
+    };
+    let position = match obj.position {
+        Some(pos) => Json::Array(vec![Json::Number(pos.x as f64), Json::Number(pos.y as f64)]),
+        None => Json::Null,
+    };
+    Json::Object(vec![
+        ("id".to_string(), Json::Number(obj.id as f64)),
+        ("text".to_string(), Json::String(obj.text.clone())),
+        (
+            "shape".to_string(),
+            Json::String(format!("{:?}", obj.shape)),
+        ),
+        ("kind".to_string(), Json::String(format!("{:?}", obj.kind))),
+        ("color".to_string(), color_json(obj.color)),
+        (
+            "color2".to_string(),
+            obj.color2.map_or(Json::Null, color_json),
+        ),
+        (
+            "rotation_speed".to_string(),
+            Json::Number(obj.rotation_speed as f64),
+        ),
+        (
+            "rotation_offset".to_string(),
+            Json::Number(obj.rotation_offset as f64),
+        ),
+        ("scale".to_string(), Json::Number(obj.scale as f64)),
+        ("visible".to_string(), Json::Bool(obj.visible)),
+        ("collapsed".to_string(), Json::Bool(obj.collapsed)),
+        ("alpha".to_string(), Json::Number(obj.alpha as f64)),
+        ("position".to_string(), position),
+        ("locked".to_string(), Json::Bool(obj.locked)),
+        ("notes".to_string(), Json::String(obj.notes.clone())),
+        ("category".to_string(), Json::String(obj.category.clone())),
+        (
+            // `[key, value]` pairs rather than an object, since keys may repeat.
+            "metadata".to_string(),
+            Json::Array(
+                obj.metadata
+                    .iter()
+                    .map(|(key, value)| {
+                        Json::Array(vec![Json::String(key.clone()), Json::String(value.clone())])
+                    })
+                    .collect(),
+            ),
+        ),
+        (
+            "outline".to_string(),
+            obj.outline.map_or(Json::Null, color_json),
+        ),
+        (
+            "outline_thickness".to_string(),
+            Json::Number(obj.outline_thickness as f64),
+        ),
+        (
+            "glyph".to_string(),
+            obj.glyph
+                .map_or(Json::Null, |c| Json::String(c.to_string())),
+        ),
+        (
+            "children".to_string(),
+            Json::Array(obj.children.iter().map(scene_object_to_json).collect()),
+        ),
+    ])
+}
+
+/// The inverse of `scene_object_to_json`. Only `id` is required; anything else that is
+/// missing falls back to what `SceneObject::new` uses.
+fn scene_object_from_json(value: &Json) -> Result<SceneObject, String> {
+    let id = value
+        .get("id")
+        .and_then(Json::as_f64)
+        .ok_or("node is missing an id")?;
+    if id.fract() != 0.0 || !(0.0..=u32::MAX as f64).contains(&id) {
+        return Err(format!("invalid node id {}", id));
+    }
+    let id = id as u32;
+    let text = value
+        .get("text")
+        .and_then(Json::as_str)
+        .unwrap_or("New Node");
+    let shape = match value.get("shape").and_then(Json::as_str) {
+        Some("Circle") => Shape::Circle,
+        Some("Triangle") => Shape::Triangle,
+        Some("Star") => Shape::Star,
+        Some("Hexagon") => Shape::Hexagon,
+        _ => Shape::Square,
+    };
+    let color_at = |key| match value.get(key).and_then(Json::as_array) {
+        Some([r, g, b, a]) => Some(Color::new(
+            r.as_f64().unwrap_or(255.0) as u8,
+            g.as_f64().unwrap_or(255.0) as u8,
+            b.as_f64().unwrap_or(255.0) as u8,
+            a.as_f64().unwrap_or(255.0) as u8,
+        )),
+        _ => None,
+    };
+    let color = color_at("color").unwrap_or(Color::WHITE);
+
+    let mut obj = SceneObject::new(id, text, shape, color);
+    let number = |key| value.get(key).and_then(Json::as_f64);
+    let flag = |key| value.get(key).and_then(Json::as_bool);
+    obj.kind = match value.get("kind").and_then(Json::as_str) {
+        Some("Group") => NodeKind::Group,
+        Some("Data") => NodeKind::Data,
+        Some("Render") => NodeKind::Render,
+        Some("Asset") => NodeKind::Asset,
+        _ => NodeKind::Plain,
+    };
+    if let Some(speed) = number("rotation_speed") {
+        obj.rotation_speed = speed as f32;
+    }
+    if let Some(offset) = number("rotation_offset") {
+        obj.rotation_offset = offset as f32;
+    }
+    if let Some(scale) = number("scale") {
+        obj.scale = scale as f32;
+    }
+    obj.visible = flag("visible").unwrap_or(obj.visible);
+    obj.collapsed = flag("collapsed").unwrap_or(obj.collapsed);
+    obj.expand_progress = if obj.collapsed { 0.0 } else { 1.0 };
+    obj.locked = flag("locked").unwrap_or(obj.locked);
+    if let Some(notes) = value.get("notes").and_then(Json::as_str) {
+        obj.notes = notes.to_string();
+    }
+    if let Some(category) = value.get("category").and_then(Json::as_str) {
+        obj.category = category.to_string();
+    }
+    if let Some(pairs) = value.get("metadata").and_then(Json::as_array) {
+        obj.metadata = pairs
+            .iter()
+            .filter_map(|pair| match pair.as_array()? {
+                [key, value] => Some((key.as_str()?.to_string(), value.as_str()?.to_string())),
+                _ => None,
+            })
+            .collect();
+    }
+    if let Some(alpha) = number("alpha") {
+        obj.alpha = alpha as u8;
+    }
+    obj.color2 = color_at("color2");
+    obj.outline = color_at("outline");
+    if let Some(thickness) = number("outline_thickness") {
+        obj.outline_thickness = thickness as f32;
+    }
+    obj.glyph = value
+        .get("glyph")
+        .and_then(Json::as_str)
+        .and_then(|glyph| glyph.chars().next());
+    if let Some([x, y]) = value.get("position").and_then(Json::as_array)
+        && let (Some(x), Some(y)) = (x.as_f64(), y.as_f64())
+    {
+        obj.position = Some(Vector2::new(x as f32, y as f32));
+    }
+    for child in value
+        .get("children")
+        .and_then(Json::as_array)
+        .unwrap_or(&[])
+    {
+        obj.children.push(scene_object_from_json(child)?);
+    }
+    Ok(obj)
+}
+
+fn tree_bounds(positions: &HashMap<u32, Vector2>) -> Option<Rectangle> {
+    const MARGIN: f32 = 60.0;
+    let (min, max) = node_extents(positions)?;
+    Some(Rectangle::new(
+        min.x - MARGIN,
+        min.y - MARGIN,
+        max.x - min.x + MARGIN * 2.0,
+        max.y - min.y + MARGIN * 2.0,
+    ))
+}
+
+/// Minimum and maximum node centres, without any padding for shapes or labels.
+fn node_extents(positions: &HashMap<u32, Vector2>) -> Option<(Vector2, Vector2)> {
+    let mut points = positions.values();
+    let first = points.next()?;
+    let (mut min, mut max) = (*first, *first);
+    for pos in points {
+        min.x = min.x.min(pos.x);
+        min.y = min.y.min(pos.y);
+        max.x = max.x.max(pos.x);
+        max.y = max.y.max(pos.y);
+    }
+    Some((min, max))
+}
+
+/// Limits a camera target so that at least one node stays on screen at the camera's zoom.
+fn clamp_target_to_tree(
+    target: Vector2,
+    camera: &Camera2D,
+    screen_size: Vector2,
+    positions: &HashMap<u32, Vector2>,
+) -> Vector2 {
+    let Some((min, max)) = node_extents(positions) else {
+        return target;
+    };
+    // The visible world span around the target, split at the camera offset.
+    let before = camera.offset / camera.zoom;
+    let after = (screen_size - camera.offset) / camera.zoom;
+    let lo = min - after + Vector2::new(NODE_SIZE, NODE_SIZE);
+    let hi = max + before - Vector2::new(NODE_SIZE, NODE_SIZE);
+    Vector2::new(
+        target.x.clamp(lo.x, hi.x.max(lo.x)),
+        target.y.clamp(lo.y, hi.y.max(lo.y)),
+    )
+}
+
+/// Recomputes the cached layout if the tree or spacing changed since the last call.
+/// Inherited rotation moves nodes every step, so it recomputes every call.
+fn refresh_layout(state: &mut State) {
+    if state.layout_dirty || state.inherit_rotation {
+        state.layout_positions = compute_layout(state);
+        state.collapse_fades.clear();
+        collect_collapse_fades(&state.scene_objects, 1.0, &mut state.collapse_fades);
+        state.layout_dirty = false;
+    }
+}
+
+/// `layout::compute_layout` with the state's tree and layout settings.
+fn compute_layout(state: &State) -> HashMap<u32, Vector2> {
+    layout::compute_layout(
+        &state.scene_objects,
+        state.x_spacing,
+        state.y_spacing,
+        state.layout_direction,
+        state.inherit_rotation,
+    )
+}
+
+/// Times `compute_layout` on a generated tree of `count` nodes with the current layout
+/// settings and prints the result. The scene is swapped back in afterwards untouched.
+fn benchmark_layout(state: &mut State, count: u32) {
+    const RUNS: u32 = 5;
+    let scene = std::mem::replace(&mut state.scene_objects, generate_stress_tree(count));
+    let mut total = 0.0;
+    let mut best = f64::MAX;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        let positions = compute_layout(state);
+        let ms = start.elapsed().as_secs_f64() * 1000.0;
+        std::hint::black_box(positions);
+        total += ms;
+        best = best.min(ms);
+    }
+    state.scene_objects = scene;
+    println!(
+        "Layout of {} nodes ({:?}): {:.2} ms average, {:.2} ms best of {}",
+        count,
+        state.layout_direction,
+        total / RUNS as f64,
+        best,
+        RUNS
+    );
+}
+
+/// A single tree of `count` nodes where each node after the root hangs off a random
+/// earlier one. The seed is fixed, so every call builds the same tree.
+fn generate_stress_tree(count: u32) -> Vec<SceneObject> {
+    let mut rng = StdRng::seed_from_u64(0x5eed);
+    let nodes = (1..=count)
+        .map(|id| SceneObject::new(id, &format!("Node {}", id), Shape::Square, Color::WHITE))
+        .collect();
+    hang_randomly(nodes, &mut rng)
+}
+
+/// Builds one tree out of `nodes`, the first as the root and each later one as the
+/// child of a random node before it.
+fn hang_randomly(mut nodes: Vec<SceneObject>, rng: &mut StdRng) -> Vec<SceneObject> {
+    let parents: Vec<usize> = (0..nodes.len())
+        .map(|i| if i == 0 { 0 } else { rng.random_range(0..i) })
+        .collect();
+    // Every parent comes before its children, so popping from the back always finds
+    // the parent still in the list.
+    while nodes.len() > 1 {
+        let index = nodes.len() - 1;
+        if let Some(child) = nodes.pop() {
+            nodes[parents[index]].children.push(child);
+        }
+    }
+    nodes
+}
+
+/// Records in `fades` every node under a collapse or expand animation, with the product
+/// of its ancestors' `expand_progress`. `fade` is that product for `objects`.
+fn collect_collapse_fades(objects: &[SceneObject], fade: f32, fades: &mut HashMap<u32, f32>) {
+    for obj in objects {
+        if fade < 1.0 {
+            fades.insert(obj.id, fade);
+        }
+        // Settled collapsed subtrees aren't laid out, so they have nothing to fade.
+        if obj.expand_progress > 0.0 {
+            collect_collapse_fades(&obj.children, fade * obj.expand_progress, fades);
+        }
+    }
+}
+
+/// Where each laid-out node is drawn while collapses animate: its offset from its
+/// parent's layout spot, scaled by the parent's `expand_progress`, added to wherever the
+/// parent is drawn. `parent` is the layout and drawn positions and progress of the
+/// node `objects` hang from.
+fn slide_positions(
+    objects: &[SceneObject],
+    parent: Option<(Vector2, Vector2, f32)>,
+    layout: &HashMap<u32, Vector2>,
     positions: &mut HashMap<u32, Vector2>,
-) -> f32 {
-    const X_SPACING: f32 = 250.0;
-    const Y_SPACING: f32 = 120.0;
-    let mut children_height = 0.0;
-    let mut child_y_cursor = y_start;
-    for child in &obj.children {
-        children_height += layout_recursive(
-            child,
-            x + X_SPACING,
-            child_y_cursor,
-            &mut child_y_cursor,
+) {
+    for obj in objects {
+        let Some(pos) = layout.get(&obj.id) else {
+            continue;
+        };
+        let drawn = match parent {
+            Some((from, drawn_from, t)) => drawn_from + (*pos - from) * t,
+            None => *pos,
+        };
+        positions.insert(obj.id, drawn);
+        slide_positions(
+            &obj.children,
+            Some((*pos, drawn, obj.expand_progress)),
+            layout,
             positions,
         );
     }
-    let my_pos = if !obj.children.is_empty() {
-        Vector2::new(x, y_start + (children_height / 2.0) - (Y_SPACING / 2.0))
-    } else {
-        Vector2::new(x, *y_cursor)
+}
+
+/// How the world should be drawn, besides the tree itself.
+struct WorldView<'a> {
+    positions: &'a HashMap<u32, Vector2>,
+    connector_style: ConnectorStyle,
+    // Which way elbows and curves leave the parent; see `connector_is_vertical`.
+    layout_direction: LayoutDirection,
+    connector_thickness: f32,
+    // Node count of each subtree, by its root's id, when connectors are weighted.
+    subtree_sizes: Option<HashMap<u32, usize>>,
+    color_by_depth: bool,
+    show_ids: bool,
+    label_plates: bool,
+    labels_rotate: bool,
+    // Ids from a root down to the selected node. Connectors along it are highlighted
+    // and every other connector is dimmed.
+    highlighted_path: Vec<u32>,
+    selected_ids: &'a HashSet<u32>,
+    // Nodes matching the find bar's query, ringed in magenta.
+    search_matches: &'a HashSet<u32>,
+    // The node open in the settings panel. Its highlight ring pulses with `time`.
+    active_id: Option<u32>,
+    time: f32,
+    // World area on screen. Nodes outside it are skipped; None draws everything.
+    visible_rect: Option<Rectangle>,
+    // Nodes the category filter keeps bright. None when no filter is set.
+    filter_kept: Option<HashSet<u32>>,
+    // See `State::collapse_fades`.
+    fades: &'a HashMap<u32, f32>,
+    theme: Theme,
+}
+
+impl WorldView<'_> {
+    /// `obj.alpha`, cut down if the category filter dims the node or it is still fading
+    /// in or out with a collapse.
+    fn alpha_of(&self, obj: &SceneObject) -> u8 {
+        let alpha = match &self.filter_kept {
+            Some(kept) if !kept.contains(&obj.id) => obj.alpha / 5,
+            _ => obj.alpha,
+        };
+        match self.fades.get(&obj.id) {
+            Some(fade) => (alpha as f32 * fade) as u8,
+            None => alpha,
+        }
+    }
+}
+
+/// One layer of the world. Each pass covers the whole tree before the next starts, so
+/// connectors never cover shapes and shapes never cover labels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DrawPass {
+    Connectors,
+    Shapes,
+    Labels,
+}
+
+/// Draws every laid-out object in world space. Shared by the window and the PNG export.
+/// Within a pass nodes follow a pre-order walk of the tree, never `positions` order, so
+/// parents sit under their children and overlaps don't flicker between frames.
+fn draw_world(
+    d_world: &mut impl RaylibDraw,
+    font: &WeakFont,
+    objects: &[SceneObject],
+    view: &WorldView,
+) {
+    for pass in [DrawPass::Connectors, DrawPass::Shapes, DrawPass::Labels] {
+        for obj in objects {
+            draw_world_recursive(d_world, font, obj, 0, view, pass);
+        }
+    }
+}
+
+/// Walks the tree so each node knows its depth. Nodes without a position (inside a
+/// collapsed subtree) are skipped along with their children.
+fn draw_world_recursive(
+    d_world: &mut impl RaylibDraw,
+    font: &WeakFont,
+    obj: &SceneObject,
+    depth: usize,
+    view: &WorldView,
+    pass: DrawPass,
+) {
+    let Some(pos) = view.positions.get(&obj.id) else {
+        return;
     };
-    positions.insert(obj.id, my_pos);
-    let my_height = if children_height > 0.0 {
-        children_height
+    // Connectors may cross the screen from off it, but nothing else a node draws can.
+    let on_screen = view
+        .visible_rect
+        .is_none_or(|rect| check_collision_point_rec(*pos, rect));
+    match pass {
+        DrawPass::Connectors => draw_node_connectors(d_world, obj, *pos, view),
+        DrawPass::Shapes if on_screen => {
+            let fill = if view.color_by_depth {
+                depth_color(depth)
+            } else {
+                obj.color
+            };
+            let is_leaf = obj.children.is_empty();
+            draw_node_shape(d_world, font, obj, *pos, fill, is_leaf, view);
+        }
+        DrawPass::Labels if on_screen && obj.visible => {
+            draw_node_labels(d_world, font, obj, *pos, view);
+        }
+        _ => {}
+    }
+    for child in &obj.children {
+        draw_world_recursive(d_world, font, child, depth + 1, view, pass);
+    }
+}
+
+/// Scales a colour's existing alpha by a node's `alpha`.
+fn with_alpha(color: Color, alpha: u8) -> Color {
+    Color {
+        a: (color.a as u16 * alpha as u16 / 255) as u8,
+        ..color
+    }
+}
+
+/// World-space grid lines covering just the visible area, plus a crosshair at the origin.
+fn draw_grid(
+    d_world: &mut impl RaylibDraw,
+    camera: &Camera2D,
+    screen_size: Vector2,
+    line_color: Color,
+) {
+    const MINOR: f32 = 50.0;
+    const MAJOR: f32 = 250.0;
+    let min = screen_to_world(Vector2::zero(), camera);
+    let max = screen_to_world(screen_size, camera);
+    let thick = 1.0 / camera.zoom;
+
+    let mut x = (min.x / MINOR).floor() * MINOR;
+    while x <= max.x {
+        let color = grid_line_color(x, MAJOR, line_color);
+        d_world.draw_line_ex(Vector2::new(x, min.y), Vector2::new(x, max.y), thick, color);
+        x += MINOR;
+    }
+    let mut y = (min.y / MINOR).floor() * MINOR;
+    while y <= max.y {
+        let color = grid_line_color(y, MAJOR, line_color);
+        d_world.draw_line_ex(Vector2::new(min.x, y), Vector2::new(max.x, y), thick, color);
+        y += MINOR;
+    }
+
+    let arm = 20.0 / camera.zoom;
+    let origin_thick = 2.0 / camera.zoom;
+    d_world.draw_line_ex(
+        Vector2::new(-arm, 0.0),
+        Vector2::new(arm, 0.0),
+        origin_thick,
+        Color::RED,
+    );
+    d_world.draw_line_ex(
+        Vector2::new(0.0, -arm),
+        Vector2::new(0.0, arm),
+        origin_thick,
+        Color::RED,
+    );
+}
+
+fn grid_line_color(coord: f32, major: f32, base: Color) -> Color {
+    if (coord / major).round() * major == coord {
+        base.alpha(0.12)
     } else {
-        Y_SPACING
+        base.alpha(0.05)
+    }
+}
+
+/// The in-progress lasso as a polyline, with a faint closing segment back to the start.
+fn draw_lasso(d_world: &mut impl RaylibDraw, points: &[Vector2], zoom: f32) {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return;
     };
-    *y_cursor = y_start + my_height;
-    my_height
+    let thick = 2.0 / zoom;
+    for pair in points.windows(2) {
+        d_world.draw_line_ex(pair[0], pair[1], thick, Color::SKYBLUE);
+    }
+    d_world.draw_line_ex(*last, *first, thick, Color::SKYBLUE.alpha(0.4));
 }
 
-/// Draws just the world representation of an object (shape, lines, details).
-fn draw_world_object(
-    d_world: &mut RaylibMode2D<RaylibDrawHandle>,
+/// The rectangle with `a` and `b` at opposite corners, in either order.
+fn region_rect(a: Vector2, b: Vector2) -> Rectangle {
+    Rectangle::new(
+        a.x.min(b.x),
+        a.y.min(b.y),
+        (a.x - b.x).abs(),
+        (a.y - b.y).abs(),
+    )
+}
+
+/// The region an Alt-drag will zoom to, in the lasso's colour.
+fn draw_zoom_region(d_world: &mut impl RaylibDraw, region: Rectangle, zoom: f32) {
+    d_world.draw_rectangle_rec(region, Color::SKYBLUE.alpha(0.1));
+    d_world.draw_rectangle_lines_ex(region, 2.0 / zoom, Color::SKYBLUE);
+}
+
+/// A fill colour for each tree level, stepping around the hue wheel.
+fn depth_color(depth: usize) -> Color {
+    Color::color_from_hsv((depth as f32 * 47.0) % 360.0, 0.6, 0.9)
+}
+
+/// Draws the lines from a node to each of its laid-out children.
+fn draw_node_connectors(
+    d_world: &mut impl RaylibDraw,
     obj: &SceneObject,
     world_pos: Vector2,
-    positions: &HashMap<u32, Vector2>,
+    view: &WorldView,
 ) {
+    let path = &view.highlighted_path;
+    // Edges into and out of the active node outrank the path, so its immediate
+    // neighbourhood stands out in the selection ring's colour.
+    let touches_active = |id: u32| view.active_id.is_some_and(|active| active == id);
     for child in &obj.children {
-        if let Some(child_pos) = positions.get(&child.id) {
-            d_world.draw_line_v(world_pos, *child_pos, Color::GRAY);
+        if let Some(child_pos) = view.positions.get(&child.id)
+            && view
+                .visible_rect
+                .is_none_or(|rect| rect.check_collision_recs(&span_rect(world_pos, *child_pos)))
+        {
+            // Weighted edges grow with the log of the subtree below them, so a leaf's
+            // edge keeps the base width.
+            let width = match &view.subtree_sizes {
+                Some(sizes) => {
+                    let size = sizes.get(&child.id).copied().unwrap_or(1);
+                    view.connector_thickness * (1.0 + (size as f32).log2())
+                }
+                None => view.connector_thickness,
+            };
+            let (color, thick) = if touches_active(obj.id) || touches_active(child.id) {
+                (Color::SKYBLUE, width + 2.0)
+            } else if path.contains(&obj.id) && path.contains(&child.id) {
+                (Color::GOLD, width + 2.0)
+            } else if path.is_empty() {
+                (view.theme.connector, width)
+            } else {
+                (view.theme.connector.alpha(0.35), width)
+            };
+            draw_connector(
+                d_world,
+                view.connector_style,
+                view.layout_direction,
+                world_pos,
+                *child_pos,
+                thick,
+                with_alpha(color, view.alpha_of(child)),
+            );
         }
     }
+}
 
-    let size = 40.0;
-    match obj.shape {
-        Shape::Square => d_world.draw_rectangle_pro(
-            Rectangle::new(world_pos.x, world_pos.y, size, size),
-            Vector2::new(size / 2.0, size / 2.0),
-            obj.current_rotation,
-            obj.color,
-        ),
-        Shape::Circle => d_world.draw_circle_v(world_pos, size / 2.0, obj.color),
-        Shape::Triangle => {
-            let angle_rad = obj.current_rotation.to_radians();
-            let cos_a = angle_rad.cos();
-            let sin_a = angle_rad.sin();
-            let p1 = Vector2::new(0.0, -size / 2.0);
-            let p2 = Vector2::new(-size / 2.0, size / 2.0);
-            let p3 = Vector2::new(size / 2.0, size / 2.0);
-            let rp1 =
-                Vector2::new(p1.x * cos_a - p1.y * sin_a, p1.x * sin_a + p1.y * cos_a) + world_pos;
-            let rp2 =
-                Vector2::new(p2.x * cos_a - p2.y * sin_a, p2.x * sin_a + p2.y * cos_a) + world_pos;
-            let rp3 =
-                Vector2::new(p3.x * cos_a - p3.y * sin_a, p3.x * sin_a + p3.y * cos_a) + world_pos;
-            d_world.draw_triangle(rp1, rp2, rp3, obj.color);
-        }
-    }
-
-    let text_size = d_world
-        .get_font_default()
-        .measure_text(&obj.text, 20.0, 1.0);
-    d_world.draw_text(
+/// Draws a node's selection rings, its filled shape and outline, or the dashed ring
+/// that stands in for a hidden node. Branches get a dot in the middle so the tree's
+/// shape reads at a glance.
+fn draw_node_shape(
+    d_world: &mut impl RaylibDraw,
+    font: &WeakFont,
+    obj: &SceneObject,
+    world_pos: Vector2,
+    fill: Color,
+    is_leaf: bool,
+    view: &WorldView,
+) {
+    let alpha = view.alpha_of(obj);
+    let fill = with_alpha(fill, alpha);
+    let size = obj.size();
+    if view.selected_ids.contains(&obj.id) {
+        d_world.draw_circle_lines_v(world_pos, size * 0.75, Color::SKYBLUE);
+    }
+    if view.search_matches.contains(&obj.id) {
+        let radius = size * 0.65;
+        d_world.draw_ring(
+            world_pos,
+            radius,
+            radius + 3.0,
+            0.0,
+            360.0,
+            48,
+            Color::MAGENTA,
+        );
+    }
+    if view.active_id == Some(obj.id) {
+        let pulse = ((view.time * 4.0).sin() + 1.0) / 2.0;
+        let radius = size * 0.8 + pulse * 4.0;
+        d_world.draw_ring(world_pos, radius, radius + 3.0, 0.0, 360.0, 48, Color::GOLD);
+    }
+    if !obj.visible {
+        // Hidden: a faint dashed ring keeps the node findable and clickable.
+        draw_dashed_circle(
+            d_world,
+            world_pos,
+            size / 2.0,
+            view.theme.connector.alpha(0.5),
+        );
+        return;
+    }
+    draw_node_body(d_world, font, obj, world_pos, fill, alpha);
+    if !is_leaf {
+        let dot_alpha = alpha as f32 / 255.0 * 0.6;
+        d_world.draw_circle_v(world_pos, 4.0, view.theme.background.alpha(dot_alpha));
+    }
+}
+
+/// A node's filled shape, plain or gradient, or its glyph, and its outline. Shared by the
+/// world and the settings panel preview.
+fn draw_node_body(
+    d: &mut impl RaylibDraw,
+    font: &WeakFont,
+    obj: &SceneObject,
+    pos: Vector2,
+    fill: Color,
+    alpha: u8,
+) {
+    match (obj.glyph, obj.color2) {
+        (Some(glyph), _) => draw_glyph(d, font, glyph, pos, obj.size(), obj.angle(), fill),
+        (None, Some(color2)) => draw_gradient_fill(d, obj, pos, fill, with_alpha(color2, alpha)),
+        (None, None) => draw_shape(d, obj.shape, pos, obj.size(), obj.angle(), fill),
+    }
+    if let Some(outline) = obj.outline {
+        draw_outline(d, obj, pos, with_alpha(outline, alpha));
+    }
+}
+
+/// Draws the text and icons around a node: id, lock, collapsed badge and name.
+fn draw_node_labels(
+    d_world: &mut impl RaylibDraw,
+    font: &WeakFont,
+    obj: &SceneObject,
+    world_pos: Vector2,
+    view: &WorldView,
+) {
+    let alpha = view.alpha_of(obj);
+    let size = obj.size();
+    if view.show_ids {
+        let id_text = format!("#{}", obj.id);
+        let id_size = font.measure_text(&id_text, 10.0, 1.0);
+        d_world.draw_text_ex(
+            font,
+            &id_text,
+            Vector2::new(
+                world_pos.x - id_size.x / 2.0,
+                world_pos.y - size / 2.0 - 14.0,
+            ),
+            10.0,
+            1.0,
+            with_alpha(view.theme.muted_text, alpha),
+        );
+    }
+
+    if obj.locked {
+        draw_lock_icon(
+            d_world,
+            world_pos + Vector2::new(-size / 2.0 - 12.0, -size / 2.0),
+            with_alpha(view.theme.muted_text, alpha),
+        );
+    }
+
+    if obj.collapsed && !obj.children.is_empty() {
+        // A badge with the hidden child count, to the right of the shape.
+        d_world.draw_text(
+            &format!("+{}", obj.children.len()),
+            (world_pos.x + size / 2.0 + 6.0) as i32,
+            (world_pos.y - 10.0) as i32,
+            20,
+            with_alpha(view.theme.muted_text, alpha),
+        );
+    }
+
+    // Float positions, so the label doesn't snap to whole world units at high zoom.
+    let label = label_rect(world_pos, size, label_width(font, &obj.text));
+    // Turning labels swing around the node's centre, which sits at this offset from the
+    // label's top-left corner. Hit testing keeps using the upright rectangle.
+    let angle = if view.labels_rotate { obj.angle() } else { 0.0 };
+    let origin = Vector2::new(world_pos.x - label.x, world_pos.y - label.y);
+    if view.label_plates && !obj.text.is_empty() {
+        // The theme's background rather than plain black, so the theme's text colour
+        // always reads on it, whatever is behind the label.
+        const PAD: f32 = 4.0;
+        let plate = Rectangle::new(
+            label.x - PAD,
+            label.y - PAD / 2.0,
+            label.width + PAD * 2.0,
+            label.height + PAD,
+        );
+        let plate_color = view.theme.background.alpha((alpha as f32 / 255.0) * 0.75);
+        if angle == 0.0 {
+            d_world.draw_rectangle_rounded(plate, 0.4, 6, plate_color);
+        } else {
+            // raylib has no rotated rounded rectangle, so turning plates are square.
+            d_world.draw_rectangle_pro(
+                Rectangle::new(world_pos.x, world_pos.y, plate.width, plate.height),
+                origin + Vector2::new(PAD, PAD / 2.0),
+                angle,
+                plate_color,
+            );
+        }
+    }
+    d_world.draw_text_pro(
+        font,
         &obj.text,
-        (world_pos.x - text_size.x / 2.0) as i32,
-        (world_pos.y + 30.0) as i32,
+        world_pos,
+        origin,
+        angle,
+        LABEL_FONT_SIZE,
+        LABEL_SPACING,
+        with_alpha(view.theme.text, alpha),
+    );
+}
+
+/// World-space width of `text` drawn as a node label.
+fn label_width(font: &WeakFont, text: &str) -> f32 {
+    font.measure_text(text, LABEL_FONT_SIZE, LABEL_SPACING).x
+}
+
+/// World-space rectangle of a node's label, as drawn by `draw_node_labels`.
+/// It sits just below a shape of side `size`.
+fn label_rect(world_pos: Vector2, size: f32, text_width: f32) -> Rectangle {
+    Rectangle::new(
+        world_pos.x - text_width / 2.0,
+        world_pos.y + size / 2.0 + 10.0,
+        text_width,
+        LABEL_FONT_SIZE,
+    )
+}
+
+/// A text box over a node's label for renaming it in place. Enter or clicking
+/// elsewhere commits the edit; Escape cancels it.
+fn draw_inline_editor(
+    d: &mut RaylibDrawHandle,
+    state: &mut State,
+    id: u32,
+    positions: &HashMap<u32, Vector2>,
+    font: &WeakFont,
+) {
+    let camera = state.camera;
+    let (Some(pos), Some(obj)) = (
+        positions.get(&id),
+        find_object_by_id_mut(&mut state.scene_objects, id),
+    ) else {
+        state.inline_edit_id = None;
+        state.active_textbox_id = None;
+        return;
+    };
+
+    let text_width = label_width(font, &obj.text_buffer);
+    let label = label_rect(*pos, obj.size(), text_width);
+    let center = world_to_screen(
+        Vector2::new(label.x + label.width / 2.0, label.y + label.height / 2.0),
+        &camera,
+    );
+    let width = (text_width * camera.zoom + 20.0).max(120.0);
+    let bounds = Rectangle::new(center.x - width / 2.0, center.y - 15.0, width, 30.0);
+
+    let clicked_away = d.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT)
+        && !check_collision_point_rec(d.get_mouse_position(), bounds);
+    if gui_text_box_safe(d, bounds, &mut obj.text_buffer) || clicked_away {
+        obj.text = obj.text_buffer.clone();
+        state.inline_edit_id = None;
+        state.active_textbox_id = None;
+    } else if d.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+        obj.text_buffer = obj.text.clone();
+        state.inline_edit_id = None;
+        state.active_textbox_id = None;
+    } else {
+        // The settings panel releases the shared text box focus on clicks outside it.
+        state.active_textbox_id = Some(id);
+    }
+}
+
+/// Screen rectangle covered by a context menu opened at `pos`.
+fn context_menu_rect(pos: Vector2) -> Rectangle {
+    Rectangle::new(pos.x, pos.y, 120.0, 25.0 * 5.0)
+}
+
+/// Draws the right-click menu for a node and turns a chosen entry into an `EditorRequest`.
+fn draw_context_menu(d: &mut RaylibDrawHandle, state: &mut State) {
+    let Some((node_id, pos)) = state.context_menu else {
+        return;
+    };
+    let Some(obj) = find_object_by_id(&state.scene_objects, node_id) else {
+        state.context_menu = None;
+        return;
+    };
+
+    let collapse_label = if obj.collapsed { "Expand" } else { "Collapse" };
+    let entries = [
+        (
+            "Add Child",
+            EditorRequest::AddChild {
+                parent_id: node_id,
+                select: false,
+            },
+        ),
+        ("Duplicate", EditorRequest::Duplicate { node_id }),
+        (
+            "Delete",
+            EditorRequest::DeleteNodes {
+                node_ids: vec![node_id],
+            },
+        ),
+        (collapse_label, EditorRequest::ToggleCollapse { node_id }),
+        ("Ungroup", EditorRequest::Ungroup { node_id }),
+    ];
+
+    let rect = context_menu_rect(pos);
+    state.gui_rects.push(rect);
+    let item_height = rect.height / entries.len() as f32;
+    for (i, (label, request)) in entries.into_iter().enumerate() {
+        let bounds = Rectangle::new(
+            rect.x,
+            rect.y + i as f32 * item_height,
+            rect.width,
+            item_height,
+        );
+        if d.gui_button(bounds, label) {
+            state.requests.push(request);
+            state.context_menu = None;
+        }
+    }
+}
+
+/// Canvas-wide view controls, drawn in the top-right corner.
+fn draw_view_toolbar(d: &mut RaylibDrawHandle, state: &mut State) {
+    const BUTTON_WIDTH: f32 = 70.0;
+    let rect = view_toolbar_rect(d.get_screen_width() as f32);
+    state.gui_rects.push(rect);
+    let x = rect.x;
+
+    d.gui_check_box(
+        Rectangle::new(x, 45.0, 20.0, 20.0),
+        "Color by depth",
+        &mut state.color_by_depth,
+    );
+
+    let spacing_before = (state.x_spacing, state.y_spacing);
+    let slider_x = x + BUTTON_WIDTH;
+    // Leaves room on the right for the value text.
+    let slider_width = BUTTON_WIDTH * 2.0 - 40.0;
+    d.gui_slider_bar(
+        Rectangle::new(slider_x, 75.0, slider_width, 20.0),
+        "X spacing",
+        &format!("{:.0}", state.x_spacing),
+        &mut state.x_spacing,
+        100.0,
+        500.0,
+    );
+    d.gui_slider_bar(
+        Rectangle::new(slider_x, 100.0, slider_width, 20.0),
+        "Y spacing",
+        &format!("{:.0}", state.y_spacing),
+        &mut state.y_spacing,
+        50.0,
+        300.0,
+    );
+    if (state.x_spacing, state.y_spacing) != spacing_before {
+        state.layout_dirty = true;
+    }
+    d.gui_slider_bar(
+        Rectangle::new(slider_x, 200.0, slider_width, 20.0),
+        "Edges",
+        &format!("{:.1}", state.connector_thickness),
+        &mut state.connector_thickness,
+        0.5,
+        6.0,
+    );
+    d.gui_check_box(
+        Rectangle::new(x, 225.0, 20.0, 20.0),
+        "Weight edges by subtree size",
+        &mut state.weight_connectors,
+    );
+
+    let mut active_style = state.connector_style as i32;
+    d.gui_toggle_group(
+        Rectangle::new(x, 10.0, BUTTON_WIDTH, 25.0),
+        "Straight;Elbow;Bezier",
+        &mut active_style,
+    );
+    state.connector_style = match active_style {
+        0 => ConnectorStyle::Straight,
+        1 => ConnectorStyle::Elbow,
+        _ => ConnectorStyle::Bezier,
+    };
+
+    let mut active_layout = state.layout_direction as i32;
+    d.gui_toggle_group(
+        Rectangle::new(x, 165.0, BUTTON_WIDTH, 25.0),
+        "Left-Right;Top-Down;Radial",
+        &mut active_layout,
+    );
+    let layout_direction = match active_layout {
+        0 => LayoutDirection::LeftRight,
+        1 => LayoutDirection::TopDown,
+        _ => LayoutDirection::Radial,
+    };
+    if layout_direction != state.layout_direction {
+        state.layout_direction = layout_direction;
+        state.layout_dirty = true;
+    }
+
+    if d.gui_button(Rectangle::new(x, 130.0, BUTTON_WIDTH, 25.0), "Align H") {
+        state
+            .requests
+            .push(EditorRequest::Align { horizontal: true });
+    }
+    if d.gui_button(
+        Rectangle::new(x + BUTTON_WIDTH, 130.0, BUTTON_WIDTH, 25.0),
+        "Align V",
+    ) {
+        state
+            .requests
+            .push(EditorRequest::Align { horizontal: false });
+    }
+}
+
+/// The find bar at the top centre: the query and a "current/total" match count.
+fn draw_search_bar(d: &mut RaylibDrawHandle, state: &mut State) {
+    let bounds = Rectangle::new(d.get_screen_width() as f32 / 2.0 - 150.0, 40.0, 240.0, 30.0);
+    state.gui_rects.push(Rectangle::new(
+        bounds.x,
+        bounds.y,
+        bounds.width + 70.0,
+        bounds.height,
+    ));
+
+    let clicked = d.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) && !d.gui_is_locked();
+    let inside = check_collision_point_rec(d.get_mouse_position(), bounds);
+    if clicked {
+        state.search_focused = inside;
+    }
+    let query_before = state.search_query.clone();
+    // Enter is handled with the other input, so the result is ignored here.
+    gui_text_line(d, bounds, &mut state.search_query, state.search_focused);
+    if state.search_query != query_before {
+        let query = state.search_query.trim().to_lowercase();
+        state.search_matches = if query.is_empty() {
+            HashSet::new()
+        } else {
+            matching_ids(&state.scene_objects, &query)
+        };
+        state.search_cursor = None;
+    }
+
+    let current = state.search_cursor.map_or(0, |i| i + 1);
+    d.draw_text(
+        &format!("{}/{}", current, state.search_matches.len()),
+        (bounds.x + bounds.width + 10.0) as i32,
+        (bounds.y + 5.0) as i32,
+        20,
+        state.theme.text,
+    );
+}
+
+/// The quick-add palette below the find bar. Enter adds a child to the selected node
+/// from the typed line and closes it; a line that doesn't parse stays open.
+fn draw_quick_add(d: &mut RaylibDrawHandle, state: &mut State) {
+    let bounds = Rectangle::new(d.get_screen_width() as f32 / 2.0 - 150.0, 80.0, 300.0, 30.0);
+    state.gui_rects.push(Rectangle::new(
+        bounds.x,
+        bounds.y,
+        bounds.width,
+        bounds.height + 24.0,
+    ));
+    let Some(parent_id) = state.active_settings_id else {
+        state.quick_add = None;
+        return;
+    };
+    let clicked = d.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) && !d.gui_is_locked();
+    if clicked && !check_collision_point_rec(d.get_mouse_position(), bounds) {
+        state.quick_add = None;
+        return;
+    }
+    let Some(line) = state.quick_add.as_mut() else {
+        return;
+    };
+    if gui_text_line(d, bounds, line, true)
+        && let Some((shape, text)) = parse_quick_add(line)
+    {
+        state.requests.push(EditorRequest::AddShapedChild {
+            parent_id,
+            shape,
+            text,
+        });
+        state.quick_add = None;
+    }
+    d.draw_text(
+        "Shape, then a name: \"circle Data\". Esc to cancel.",
+        bounds.x as i32,
+        (bounds.y + bounds.height + 6.0) as i32,
+        10,
+        state.theme.muted_text,
+    );
+}
+
+/// Splits a quick-add line into a shape, named by its first word in any case, and the
+/// rest as the node's name. None if the first word isn't a shape.
+fn parse_quick_add(line: &str) -> Option<(Shape, String)> {
+    let line = line.trim();
+    let (word, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let shape = match word.to_lowercase().as_str() {
+        "square" => Shape::Square,
+        "circle" => Shape::Circle,
+        "triangle" => Shape::Triangle,
+        "star" => Shape::Star,
+        "hexagon" => Shape::Hexagon,
+        _ => return None,
+    };
+    Some((shape, rest.trim().to_string()))
+}
+
+/// Screen area of the view toolbar; clicks inside it don't reach the canvas.
+fn view_toolbar_rect(screen_width: f32) -> Rectangle {
+    const WIDTH: f32 = 70.0 * 3.0;
+    Rectangle::new(screen_width - WIDTH - 14.0, 10.0, WIDTH, 235.0)
+}
+
+/// Camera target and zoom that fit node `id` and its visible children on a screen of
+/// `screen_size`, with a margin. None if the node has no layout slot.
+fn frame_node(state: &State, id: u32, screen_size: Vector2) -> Option<(Vector2, f32)> {
+    const MARGIN: f32 = 100.0;
+    let obj = find_object_by_id(&state.scene_objects, id)?;
+    let center = *state.layout_positions.get(&id)?;
+    let (mut min, mut max) = (center, center);
+    for child in visible_children(obj) {
+        if let Some(pos) = state.layout_positions.get(&child.id) {
+            min = Vector2::new(min.x.min(pos.x), min.y.min(pos.y));
+            max = Vector2::new(max.x.max(pos.x), max.y.max(pos.y));
+        }
+    }
+    let size = max - min + Vector2::new(MARGIN, MARGIN) * 2.0;
+    let zoom = (screen_size.x / size.x)
+        .min(screen_size.y / size.y)
+        .clamp(MIN_ZOOM, MAX_ZOOM);
+    Some(((min + max) / 2.0, zoom))
+}
+
+/// Camera target that puts `obj_pos` in the middle of the area right of the settings panel.
+fn panel_view_target(camera: &Camera2D, obj_pos: Vector2, screen_width: f32) -> Vector2 {
+    let viewport_center_x = (screen_width / 2.0 + screen_width) / 2.0;
+    let offset_x = (viewport_center_x - camera.offset.x) / camera.zoom;
+    Vector2::new(obj_pos.x - offset_x, obj_pos.y)
+}
+
+/// Whether `pos` is over GUI drawn last frame, which takes its own clicks.
+fn over_gui(state: &State, pos: Vector2) -> bool {
+    state
+        .gui_rects
+        .iter()
+        .any(|rect| check_collision_point_rec(pos, *rect))
+}
+
+/// Screen rectangle of the settings panel with its corner at `pos`, kept on screen far
+/// enough that the title bar can still be grabbed.
+fn settings_panel_rect(screen: Vector2, pos: Vector2) -> Rectangle {
+    const PADDING: f32 = 20.0;
+    const WIDTH: f32 = 400.0;
+    const GRIP: f32 = 40.0;
+    const TITLE_HEIGHT: f32 = 24.0;
+    // The panel runs down to the bottom padding from wherever its top is, so the
+    // scroll area can always bring its last controls on screen.
+    let y = pos
+        .y
+        .clamp(0.0, (screen.y - TITLE_HEIGHT - PADDING).max(0.0));
+    Rectangle::new(
+        pos.x
+            .clamp(GRIP - WIDTH, (screen.x - GRIP).max(GRIP - WIDTH)),
+        y,
+        WIDTH,
+        screen.y - y - PADDING,
+    )
+}
+
+/// Screen rectangle of the button that shows and clears the category filter, at the
+/// bottom centre so it clears both the settings panel and the minimap.
+fn filter_banner_rect(screen_size: Vector2) -> Rectangle {
+    Rectangle::new(
+        screen_size.x / 2.0 - 120.0,
+        screen_size.y - 40.0,
+        240.0,
+        30.0,
+    )
+}
+
+/// The modal "are you sure" box for `pending_confirm`, over a dimmed screen.
+fn draw_confirm_dialog(d: &mut RaylibDrawHandle, state: &mut State) {
+    let Some(EditorRequest::DeleteNodes { node_ids }) = state.pending_confirm.clone() else {
+        state.pending_confirm = None;
+        return;
+    };
+    let objects: Vec<&SceneObject> = node_ids
+        .iter()
+        .filter_map(|id| find_object_by_id(&state.scene_objects, *id))
+        .collect();
+    let message = match objects.as_slice() {
+        [] => {
+            state.pending_confirm = None;
+            return;
+        }
+        [obj] => format!(
+            "Delete {} nodes?",
+            tree_stats(std::slice::from_ref(*obj)).node_count
+        ),
+        _ => format!("Delete {} nodes and their children?", objects.len()),
+    };
+
+    let (width, height) = (d.get_screen_width() as f32, d.get_screen_height() as f32);
+    d.draw_rectangle(0, 0, width as i32, height as i32, Color::BLACK.alpha(0.5));
+    let bounds = Rectangle::new(width / 2.0 - 150.0, height / 2.0 - 60.0, 300.0, 120.0);
+    state.gui_rects.push(bounds);
+    let choice = d.gui_message_box(bounds, "Delete subtree", &message, "Delete;Cancel");
+    // 0 is the close button, then the buttons in order; -1 means no click yet.
+    match choice {
+        1 => {
+            state
+                .requests
+                .push(EditorRequest::DeleteConfirmed { node_ids });
+            state.pending_confirm = None;
+        }
+        0 | 2 => state.pending_confirm = None,
+        _ => {}
+    }
+}
+
+/// Details of the hovered node, shown beside the cursor once it has rested there.
+/// Collapsed nodes also list their hidden children's names.
+fn draw_tooltip(d: &mut RaylibDrawHandle, state: &State) {
+    const HOVER_DELAY: f32 = 0.5;
+    let dragging = state.panning
+        || !state.lasso.is_empty()
+        || d.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT);
+    if dragging || state.hover_time < HOVER_DELAY || state.context_menu.is_some() {
+        return;
+    }
+    let Some(obj) = state
+        .hover_id
+        .and_then(|id| find_object_by_id(&state.scene_objects, id))
+    else {
+        return;
+    };
+
+    let mut lines = vec![
+        format!("#{} {}", obj.id, obj.text),
+        format!("{:?}, {} children", obj.shape, obj.children.len()),
+    ];
+    // A collapsed node lists the children it hides, so the branch can be peeked into
+    // without expanding it.
+    if obj.collapsed && !obj.children.is_empty() {
+        const MAX_LISTED: usize = 8;
+        lines.extend(
+            obj.children
+                .iter()
+                .take(MAX_LISTED)
+                .map(|child| format!("  {}", child.text)),
+        );
+        if obj.children.len() > MAX_LISTED {
+            lines.push(format!("  ...and {} more", obj.children.len() - MAX_LISTED));
+        }
+    }
+    let width = lines
+        .iter()
+        .map(|line| d.measure_text(line, 20))
+        .max()
+        .unwrap_or(0);
+    let height = lines.len() as i32 * 24 + 4;
+    let pos = d.get_mouse_position() + Vector2::new(16.0, 16.0);
+    let (x, y) = (pos.x as i32, pos.y as i32);
+    d.draw_rectangle(x, y, width + 12, height, Color::BLACK.alpha(0.8));
+    d.draw_rectangle_lines(x, y, width + 12, height, Color::GRAY);
+    for (i, line) in lines.iter().enumerate() {
+        d.draw_text(line, x + 6, y + 4 + i as i32 * 24, 20, Color::WHITE);
+    }
+}
+
+/// Every binding, for the help overlay. Add new shortcuts here.
+const HELP_BINDINGS: &[(&str, &str)] = &[
+    ("Left click", "Select a node and open its settings"),
+    (
+        "Double-click",
+        "Rename a node, or add a root on empty canvas",
+    ),
+    ("Shift + left drag", "Lasso-select nodes"),
+    ("Alt + left drag", "Zoom to fit the dragged region"),
+    ("Left drag", "Move a node (free layout only)"),
+    (
+        "Right click",
+        "Node menu: add child, duplicate, delete, collapse, ungroup",
+    ),
+    ("Right/Middle drag", "Pan"),
+    ("Scroll wheel", "Zoom at the cursor"),
+    ("Ctrl+Scroll wheel", "Scale the selected node"),
+    ("Drag panel title", "Move the settings panel"),
+    ("Drop a .json file", "Insert a saved template"),
+    (
+        "W A S D / Arrows",
+        "Pan (arrows only with nothing selected)",
+    ),
+    ("= / -", "Zoom in / out"),
+    ("0", "Reset the zoom to 100% around the cursor"),
+    ("1..9", "Recall a camera bookmark"),
+    ("Shift + 1..9", "Store a camera bookmark"),
+    ("Alt + 1..9", "Show that many levels of the tree"),
+    ("Shift + C / Shift + E", "Collapse / expand everything"),
+    ("Ctrl + C / Ctrl + V", "Copy / paste the selected subtree"),
+    (
+        "Ctrl + A / Ctrl + I",
+        "Select every node / invert the selection",
+    ),
+    (
+        "Ctrl + G",
+        "Group the lasso selection (or selected node) under a new node",
+    ),
+    (
+        "Ctrl + Shift + G",
+        "Ungroup: replace the selected node with its children",
+    ),
+    (
+        "Ctrl + Up / Down",
+        "Move the selected node among its siblings",
+    ),
+    (
+        "Shift + H / Shift + V",
+        "Align the lasso selection horizontally / vertically",
+    ),
+    ("Delete", "Delete the selected nodes"),
+    (
+        "Insert / Ctrl + N",
+        "Add a child to the selected node and select it",
+    ),
+    ("F", "Toggle free layout"),
+    (
+        "Shift + F",
+        "Frame the node under the cursor and its children",
+    ),
+    (
+        "Ctrl + F",
+        "Find nodes by name; Enter steps through matches, Esc clears",
+    ),
+    (
+        "Tab",
+        "Quick-add a child to the selected node: type \"circle Data\", Enter",
+    ),
+    (
+        "Ctrl + Tab / Ctrl + Shift + Tab",
+        "Move focus between panel controls; Enter / Space uses it, Left / Right steps sliders",
+    ),
+    ("M", "Toggle smooth camera motion"),
+    ("[ / ]", "Halve / double the animation speed"),
+    ("G", "Toggle the grid"),
+    ("T", "Switch between the dark and light canvas themes"),
+    (
+        "R",
+        "Toggle children turning and orbiting with their parent",
+    ),
+    ("N", "Toggle node ids"),
+    ("L", "Toggle the plates behind node names"),
+    ("K", "Toggle node names turning with their node"),
+    ("I", "Toggle tree statistics"),
+    ("`", "Toggle the performance overlay"),
+    (
+        "Shift + B",
+        "Time the layout of a 10,000-node test tree (printed to stdout)",
+    ),
+    ("Ctrl + S", "Save the whole tree to scene.json"),
+    (
+        "Ctrl + O",
+        "Load the tree from scene.json, replacing this one",
+    ),
+    ("P", "Export the tree to tree.png"),
+    ("Shift + P", "Export the tree structure to tree.dot"),
+    (
+        "Ctrl + P",
+        "Export the selected subtree, or the whole tree, to tree.svg",
+    ),
+    ("F1 / ?", "Toggle this help"),
+    ("Esc", "Cancel an edit, close this help, or quit"),
+];
+
+/// The scrollable list of `HELP_BINDINGS` over a dimmed screen.
+fn draw_help_overlay(d: &mut RaylibDrawHandle, state: &mut State) {
+    const ROW_HEIGHT: f32 = 26.0;
+    const KEY_COLUMN: f32 = 220.0;
+    let (width, height) = (d.get_screen_width() as f32, d.get_screen_height() as f32);
+    d.draw_rectangle(0, 0, width as i32, height as i32, Color::BLACK.alpha(0.6));
+
+    let panel = Rectangle::new(width / 2.0 - 320.0, 40.0, 640.0, height - 80.0);
+    d.draw_rectangle_rec(panel, Color::from_hex("2b2b2b").unwrap());
+    d.draw_rectangle_lines_ex(panel, 1.0, Color::GRAY);
+    d.draw_text(
+        "Shortcuts (F1 or Esc to close)",
+        panel.x as i32 + 16,
+        panel.y as i32 + 12,
         20,
         Color::WHITE,
     );
-}
 
-/// Draws the main, interactive settings panel for the active node.
-fn draw_settings_panel(
-    d: &mut RaylibDrawHandle,
-    active_settings_id: &mut Option<u32>,
-    requests: &mut Vec<EditorRequest>,
-    active_textbox_id: &mut Option<u32>,
-    obj: &mut SceneObject,
-) {
-    let panel_width = 400.0;
-    let padding = 20.0;
-    let window_rect = Rectangle::new(
-        padding,
-        padding,
-        panel_width,
-        d.get_screen_height() as f32 - padding * 2.0,
+    let list = Rectangle::new(
+        panel.x + 16.0,
+        panel.y + 48.0,
+        panel.width - 32.0,
+        panel.height - 64.0,
+    );
+    let content_height = HELP_BINDINGS.len() as f32 * ROW_HEIGHT;
+    state.help_scroll = state
+        .help_scroll
+        .clamp(0.0, (content_height - list.height).max(0.0));
+
+    let mut clipped = d.begin_scissor_mode(
+        list.x as i32,
+        list.y as i32,
+        list.width as i32,
+        list.height as i32,
+    );
+    for (i, (keys, action)) in HELP_BINDINGS.iter().enumerate() {
+        let y = (list.y + i as f32 * ROW_HEIGHT - state.help_scroll) as i32;
+        clipped.draw_text(keys, list.x as i32, y, 20, Color::GOLD);
+        clipped.draw_text(
+            action,
+            (list.x + KEY_COLUMN) as i32,
+            y,
+            20,
+            Color::LIGHTGRAY,
+        );
+    }
+}
+
+/// FPS, frame time and fixed-step count, just under the help text.
+fn draw_perf_overlay(d: &mut RaylibDrawHandle, state: &State) {
+    let text = format!(
+        "FPS: {}  Frame: {:.2} ms  Steps: {}  Speed: {}x",
+        d.get_fps(),
+        d.get_frame_time() * 1000.0,
+        state.steps_this_frame,
+        state.time_scale
+    );
+    let width = d.measure_text(&text, 20);
+    d.draw_rectangle(6, 34, width + 8, 24, Color::BLACK.alpha(0.6));
+    d.draw_text(&text, 10, 36, 20, Color::LIME);
+}
+
+/// The current zoom as a percentage, in the bottom-left corner, beside the settings
+/// panel when the panel covers that corner.
+fn draw_zoom_readout(d: &mut RaylibDrawHandle, state: &State) {
+    let screen_size = Vector2::new(d.get_screen_width() as f32, d.get_screen_height() as f32);
+    let height = screen_size.y;
+    let panel = settings_panel_rect(screen_size, state.panel.pos);
+    let corner = Rectangle::new(10.0, height - 28.0, 120.0, 20.0);
+    let x = if state.active_settings_id.is_some() && panel.check_collision_recs(&corner) {
+        panel.x + panel.width + 10.0
+    } else {
+        10.0
+    };
+    d.draw_text(
+        &format!("Zoom: {:.0}%", state.camera.zoom * 100.0),
+        x as i32,
+        height as i32 - 28,
+        20,
+        state.theme.muted_text,
+    );
+}
+
+/// Tree statistics, right-aligned below the view toolbar.
+fn draw_stats(d: &mut RaylibDrawHandle, state: &State) {
+    let stats = tree_stats(&state.scene_objects);
+    let selected = match state.active_settings_id {
+        Some(id) => id.to_string(),
+        None => "none".to_string(),
+    };
+    let lines = [
+        format!("Nodes: {}", stats.node_count),
+        format!("Max depth: {}", stats.max_depth),
+        format!("Leaves: {}", stats.leaf_count),
+        format!("Selected: {}", selected),
+    ];
+    let right = d.get_screen_width() - 14;
+    for (i, line) in lines.iter().enumerate() {
+        let width = d.measure_text(line, 20);
+        d.draw_text(
+            line,
+            right - width,
+            260 + i as i32 * 24,
+            20,
+            state.theme.text,
+        );
+    }
+}
+
+/// A settings panel control that Ctrl+Tab can give the keyboard focus to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PanelControl {
+    Name,
+    Notes,
+    Category,
+    Filter,
+    AddField,
+    Kind,
+    Shape,
+    Glyph,
+    RotationSpeed,
+    RotationOffset,
+    Scale,
+    ApplyColor,
+    Color,
+    Gradient,
+    Opacity,
+    Outline,
+    Visible,
+    Locked,
+    AddChild,
+    Delete,
+    SaveTemplate,
+    Pin,
+}
+
+/// Tab order of the settings panel's keyboard-focusable controls, top to bottom. The
+/// metadata cells and the colour pickers are left to the mouse.
+const PANEL_CONTROLS: [PanelControl; 22] = [
+    PanelControl::Name,
+    PanelControl::Notes,
+    PanelControl::Category,
+    PanelControl::Filter,
+    PanelControl::AddField,
+    PanelControl::Kind,
+    PanelControl::Shape,
+    PanelControl::Glyph,
+    PanelControl::RotationSpeed,
+    PanelControl::RotationOffset,
+    PanelControl::Scale,
+    PanelControl::ApplyColor,
+    PanelControl::Color,
+    PanelControl::Gradient,
+    PanelControl::Opacity,
+    PanelControl::Outline,
+    PanelControl::Visible,
+    PanelControl::Locked,
+    PanelControl::AddChild,
+    PanelControl::Delete,
+    PanelControl::SaveTemplate,
+    PanelControl::Pin,
+];
+
+/// One-click colours above the settings panel's picker: the sample tree's colours
+/// first, then a few more.
+const COLOR_SWATCHES: [Color; 14] = [
+    Color::RED,
+    Color::BLUE,
+    Color::GREEN,
+    Color::YELLOW,
+    Color::ORANGE,
+    Color::PURPLE,
+    Color::PINK,
+    Color::MAROON,
+    Color::LIME,
+    Color::SKYBLUE,
+    Color::DARKBLUE,
+    Color::BROWN,
+    Color::GRAY,
+    Color::WHITE,
+];
+
+/// What the settings panel shows that it can't work out from the node alone.
+struct PanelContext<'a> {
+    color_by_depth: bool,
+    // How far down the tree the node is; roots are depth 1.
+    depth: usize,
+    filter_category: Option<&'a str>,
+    panel: PanelState,
+    // Canvas colour, behind the shape preview.
+    background: Color,
+    // The find bar or quick-add palette has the keyboard, so focus keys are ignored.
+    typing_elsewhere: bool,
+}
+
+/// Draws the main, interactive settings panel for the active node. The controls scroll
+/// when the window is too short for them, and dragging the title bar moves the panel.
+/// Returns the panel's state for the next frame.
+fn draw_settings_panel(
+    d: &mut RaylibDrawHandle,
+    active_settings_id: &mut Option<u32>,
+    requests: &mut Vec<EditorRequest>,
+    active_textbox_id: &mut Option<u32>,
+    panel_field: &mut PanelField,
+    context: &PanelContext,
+    obj: &mut SceneObject,
+) -> PanelState {
+    let color_by_depth = context.color_by_depth;
+    let mut panel = context.panel;
+    let mouse = d.get_mouse_position();
+    let screen_size = Vector2::new(d.get_screen_width() as f32, d.get_screen_height() as f32);
+    let window_rect = settings_panel_rect(screen_size, panel.pos);
+    // The title bar, minus the close button.
+    let title_bar = Rectangle::new(window_rect.x, window_rect.y, window_rect.width - 24.0, 24.0);
+    if !d.gui_is_locked()
+        && d.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT)
+        && check_collision_point_rec(mouse, title_bar)
+    {
+        panel.drag = Some(mouse - Vector2::new(window_rect.x, window_rect.y));
+    }
+    if let Some(grab) = panel.drag {
+        if d.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT) {
+            panel.pos = mouse - grab;
+        } else {
+            panel.drag = None;
+        }
+    }
+    let window_rect = settings_panel_rect(screen_size, panel.pos);
+    panel.pos = Vector2::new(window_rect.x, window_rect.y);
+
+    if panel.node_id != Some(obj.id) {
+        panel.node_id = Some(obj.id);
+        panel.offset = Vector2::zero();
+        panel.focus_index = None;
+    }
+
+    // Ctrl+Tab (Ctrl+Shift+Tab backwards) moves the keyboard focus through
+    // `PANEL_CONTROLS`, since plain Tab opens quick-add, and Enter or Space uses the
+    // focused one. A click or Esc drops the focus.
+    let keys_free = active_textbox_id.is_none() && !context.typing_elsewhere && !d.gui_is_locked();
+    let ctrl_down = d.is_key_down(KeyboardKey::KEY_LEFT_CONTROL)
+        || d.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL);
+    let mut focus_moved = false;
+    if keys_free && ctrl_down && d.is_key_pressed(KeyboardKey::KEY_TAB) {
+        let backwards = d.is_key_down(KeyboardKey::KEY_LEFT_SHIFT)
+            || d.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+        let count = PANEL_CONTROLS.len();
+        panel.focus_index = Some(match (panel.focus_index, backwards) {
+            (None, false) => 0,
+            (None, true) => count - 1,
+            (Some(i), false) => (i + 1) % count,
+            (Some(i), true) => (i + count - 1) % count,
+        });
+        focus_moved = true;
+    }
+    if d.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT)
+        || (keys_free && d.is_key_pressed(KeyboardKey::KEY_ESCAPE))
+    {
+        panel.focus_index = None;
+    }
+    let focus = panel.focus_index.map(|i| PANEL_CONTROLS[i]);
+    let activated = keys_free
+        && focus.is_some()
+        && (d.is_key_pressed(KeyboardKey::KEY_ENTER) || d.is_key_pressed(KeyboardKey::KEY_SPACE));
+    // Left and Right step a focused slider: -1, 0 or 1.
+    let step = if !keys_free {
+        0.0
+    } else if d.is_key_pressed(KeyboardKey::KEY_RIGHT) {
+        1.0
+    } else if d.is_key_pressed(KeyboardKey::KEY_LEFT) {
+        -1.0
+    } else {
+        0.0
+    };
+    // The scroll panel's status bar doubles as the title bar; the width leaves room for
+    // the vertical scrollbar so no horizontal one appears.
+    let content = Rectangle::new(0.0, 0.0, window_rect.width - 16.0, panel.content_height);
+    let (_, view, offset) = d.gui_scroll_panel(
+        window_rect,
+        &format!("Settings: {}", obj.text),
+        content,
+        panel.offset,
+        Rectangle::default(),
+    );
+    panel.offset = offset;
+
+    let close_bounds = Rectangle::new(
+        window_rect.x + window_rect.width - 20.0,
+        window_rect.y + 3.0,
+        18.0,
+        18.0,
     );
-
-    if d.gui_window_box(window_rect, &format!("Settings: {}", obj.text)) {
+    if d.gui_button(close_bounds, "x") {
+        // Closing the panel counts as leaving the text box.
+        if *active_textbox_id == Some(obj.id) {
+            obj.text = obj.text_buffer.clone();
+            *active_textbox_id = None;
+            *panel_field = PanelField::Name;
+        }
         *active_settings_id = None;
+        panel.focus_index = None;
+    }
+
+    // Controls scrolled out of `view` are clipped, and the GUI is locked while the cursor
+    // is over the panel's column outside `view`, so hidden controls can't be clicked.
+    let mut clipped = d.begin_scissor_mode(
+        view.x as i32,
+        view.y as i32,
+        view.width as i32,
+        view.height as i32,
+    );
+    let d: &mut RaylibDrawHandle = &mut clipped;
+    let column = Rectangle::new(
+        window_rect.x,
+        0.0,
+        window_rect.width,
+        d.get_screen_height() as f32,
+    );
+    // Rings the focused control and, the frame focus reaches it, scrolls it into view.
+    let mark_focus = |d: &mut RaylibDrawHandle, panel: &mut PanelState, bounds: Rectangle| {
+        d.draw_rectangle_lines_ex(
+            Rectangle::new(
+                bounds.x - 3.0,
+                bounds.y - 3.0,
+                bounds.width + 6.0,
+                bounds.height + 6.0,
+            ),
+            2.0,
+            Color::GOLD,
+        );
+        if focus_moved {
+            if bounds.y < view.y {
+                panel.offset.y += view.y - bounds.y + 10.0;
+            } else if bounds.y + bounds.height > view.y + view.height {
+                panel.offset.y -= bounds.y + bounds.height - (view.y + view.height) + 10.0;
+            }
+        }
+    };
+    let lock_hidden = !d.gui_is_locked()
+        && check_collision_point_rec(mouse, column)
+        && !check_collision_point_rec(mouse, view);
+    if lock_hidden {
+        d.gui_lock();
     }
 
+    let content_top = window_rect.y + 24.0 + panel.offset.y;
     let base_x = window_rect.x + 10.0;
-    let mut current_y = window_rect.y + 40.0;
+    let mut current_y = content_top + 16.0;
+
+    // The node as the world draws it, rotation included, on the canvas colour.
+    let preview = Rectangle::new(base_x, current_y, window_rect.width - 20.0, 80.0);
+    d.draw_rectangle_rec(preview, context.background);
+    d.draw_rectangle_lines_ex(preview, 1.0, Color::GRAY);
+    let fill = if color_by_depth {
+        depth_color(context.depth - 1)
+    } else {
+        obj.color
+    };
+    let font = d.get_font_default();
+    draw_node_body(
+        d,
+        &font,
+        obj,
+        Vector2::new(
+            preview.x + preview.width / 2.0,
+            preview.y + preview.height / 2.0,
+        ),
+        with_alpha(fill, obj.alpha),
+        obj.alpha,
+    );
+    current_y += 90.0;
 
     d.gui_label(Rectangle::new(base_x, current_y, 100.0, 20.0), "Name:");
     current_y += 25.0;
 
     let textbox_bounds = Rectangle::new(base_x, current_y, window_rect.width - 20.0, 30.0);
-    if gui_text_box_safe(d, textbox_bounds, &mut obj.text_buffer) {
-        obj.text = obj.text_buffer.clone();
-        *active_textbox_id = None;
-    } else if check_collision_point_rec(d.get_mouse_position(), textbox_bounds)
-        && d.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT)
+    // `text` is the name. The buffer only holds typing while a rename is open, and
+    // otherwise follows `text`, so changes made any other way show up here.
+    let renaming = *active_textbox_id == Some(obj.id) && *panel_field == PanelField::Name;
+    if !renaming && obj.text_buffer != obj.text {
+        obj.text_buffer.clone_from(&obj.text);
+    }
+    if obj.locked {
+        d.gui_disable();
+        d.gui_text_box(textbox_bounds, &mut obj.text_buffer, false);
+        d.gui_enable();
+    } else if *panel_field != PanelField::Name {
+        // Typing goes to another field, so just show the name.
+        d.gui_text_box(textbox_bounds, &mut obj.text_buffer, false);
+    } else {
+        let editing = *active_textbox_id == Some(obj.id);
+        let clicked =
+            d.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) && !d.gui_is_locked();
+        let inside = check_collision_point_rec(d.get_mouse_position(), textbox_bounds);
+        let entered = gui_text_box_safe(d, textbox_bounds, &mut obj.text_buffer);
+        if entered || (editing && clicked && !inside) {
+            // Enter, or a click anywhere else (the world included), commits.
+            obj.text = obj.text_buffer.clone();
+            *active_textbox_id = None;
+        } else if editing && d.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+            obj.text_buffer = obj.text.clone();
+            *active_textbox_id = None;
+        } else if clicked && inside {
+            *active_textbox_id = Some(obj.id);
+        }
+    }
+    if focus == Some(PanelControl::Name) {
+        mark_focus(d, &mut panel, textbox_bounds);
+        // Opens the rename from the next frame, so this Enter doesn't also commit it.
+        if activated && !obj.locked && *active_textbox_id != Some(obj.id) {
+            *panel_field = PanelField::Name;
+            *active_textbox_id = Some(obj.id);
+        }
+    }
+    current_y += 35.0;
+
+    let descendants = tree_stats(std::slice::from_ref(obj)).node_count - 1;
+    d.gui_label(
+        Rectangle::new(base_x, current_y, window_rect.width - 20.0, 20.0),
+        &format!("Descendants: {}   Depth: {}", descendants, context.depth),
+    );
+    current_y += 25.0;
+
+    d.gui_label(Rectangle::new(base_x, current_y, 100.0, 20.0), "Notes:");
+    current_y += 25.0;
+    let notes_bounds = Rectangle::new(base_x, current_y, window_rect.width - 20.0, 60.0);
+    let clicked = d.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) && !d.gui_is_locked();
+    let inside = check_collision_point_rec(d.get_mouse_position(), notes_bounds);
+    let editing = *panel_field == PanelField::Notes;
+    if editing && (d.is_key_pressed(KeyboardKey::KEY_ESCAPE) || (clicked && !inside) || obj.locked)
     {
+        *panel_field = PanelField::Name;
+        *active_textbox_id = None;
+    } else if clicked && inside && !obj.locked {
+        *panel_field = PanelField::Notes;
         *active_textbox_id = Some(obj.id);
-    } else if *active_textbox_id == Some(obj.id)
-        && d.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT)
+    }
+    gui_text_area(
+        d,
+        notes_bounds,
+        &mut obj.notes,
+        *panel_field == PanelField::Notes,
+    );
+    if focus == Some(PanelControl::Notes) {
+        mark_focus(d, &mut panel, notes_bounds);
+        if activated && !obj.locked {
+            *panel_field = PanelField::Notes;
+            *active_textbox_id = Some(obj.id);
+        }
+    }
+    current_y += 70.0;
+
+    d.gui_label(Rectangle::new(base_x, current_y, 100.0, 20.0), "Category:");
+    current_y += 25.0;
+    let category_bounds = Rectangle::new(base_x, current_y, window_rect.width - 150.0, 30.0);
+    let inside = check_collision_point_rec(d.get_mouse_position(), category_bounds);
+    let editing = *panel_field == PanelField::Category;
+    let entered = gui_text_line(d, category_bounds, &mut obj.category, editing);
+    if editing
+        && (entered
+            || d.is_key_pressed(KeyboardKey::KEY_ESCAPE)
+            || (clicked && !inside)
+            || obj.locked)
     {
-        obj.text = obj.text_buffer.clone();
+        *panel_field = PanelField::Name;
         *active_textbox_id = None;
+    } else if clicked && inside && !obj.locked {
+        *panel_field = PanelField::Category;
+        *active_textbox_id = Some(obj.id);
+    }
+    if focus == Some(PanelControl::Category) {
+        mark_focus(d, &mut panel, category_bounds);
+        if activated && !obj.locked {
+            *panel_field = PanelField::Category;
+            *active_textbox_id = Some(obj.id);
+        }
+    }
+    let filter_bounds = Rectangle::new(
+        window_rect.x + window_rect.width - 130.0,
+        current_y,
+        120.0,
+        30.0,
+    );
+    let filter_focused = focus == Some(PanelControl::Filter);
+    if filter_focused {
+        mark_focus(d, &mut panel, filter_bounds);
+    }
+    let category = obj.category.trim();
+    if context.filter_category == Some(category) {
+        if d.gui_button(filter_bounds, "Clear filter") || (filter_focused && activated) {
+            requests.push(EditorRequest::SetCategoryFilter { category: None });
+        }
+    } else {
+        if category.is_empty() {
+            d.gui_disable();
+        }
+        if d.gui_button(filter_bounds, "Filter")
+            || (filter_focused && activated && !category.is_empty())
+        {
+            requests.push(EditorRequest::SetCategoryFilter {
+                category: Some(category.to_string()),
+            });
+        }
+        d.gui_enable();
+    }
+    current_y += 40.0;
+
+    d.gui_label(Rectangle::new(base_x, current_y, 100.0, 20.0), "Metadata:");
+    current_y += 25.0;
+    let key_width = 140.0;
+    let value_width = window_rect.width - 20.0 - key_width - 50.0;
+    let mut remove_row = None;
+    for (row, (key, value)) in obj.metadata.iter_mut().enumerate() {
+        let cells = [
+            (
+                PanelField::MetadataKey(row),
+                Rectangle::new(base_x, current_y, key_width, 30.0),
+                key,
+            ),
+            (
+                PanelField::MetadataValue(row),
+                Rectangle::new(base_x + key_width + 5.0, current_y, value_width, 30.0),
+                value,
+            ),
+        ];
+        for (field, bounds, text) in cells {
+            let inside = check_collision_point_rec(d.get_mouse_position(), bounds);
+            let editing = *panel_field == field;
+            let entered = gui_text_line(d, bounds, text, editing);
+            if editing
+                && (entered
+                    || d.is_key_pressed(KeyboardKey::KEY_ESCAPE)
+                    || (clicked && !inside)
+                    || obj.locked)
+            {
+                *panel_field = PanelField::Name;
+                *active_textbox_id = None;
+            } else if clicked && inside && !obj.locked {
+                *panel_field = field;
+                *active_textbox_id = Some(obj.id);
+            }
+        }
+        if obj.locked {
+            d.gui_disable();
+        }
+        if d.gui_button(
+            Rectangle::new(
+                window_rect.x + window_rect.width - 50.0,
+                current_y,
+                40.0,
+                30.0,
+            ),
+            "x",
+        ) {
+            remove_row = Some(row);
+        }
+        d.gui_enable();
+        current_y += 35.0;
+    }
+    if let Some(row) = remove_row
+        && !obj.locked
+    {
+        obj.metadata.remove(row);
+        // Rows below shift up, so a focused cell may now be a different one.
+        if matches!(
+            *panel_field,
+            PanelField::MetadataKey(_) | PanelField::MetadataValue(_)
+        ) {
+            *panel_field = PanelField::Name;
+            *active_textbox_id = None;
+        }
+    }
+    if obj.locked {
+        d.gui_disable();
+    }
+    let add_field_bounds = Rectangle::new(base_x, current_y, 150.0, 30.0);
+    let add_field_focused = focus == Some(PanelControl::AddField);
+    if add_field_focused {
+        mark_focus(d, &mut panel, add_field_bounds);
     }
+    if d.gui_button(add_field_bounds, "Add Field")
+        || (add_field_focused && activated && !obj.locked)
+    {
+        obj.metadata.push((String::new(), String::new()));
+        *panel_field = PanelField::MetadataKey(obj.metadata.len() - 1);
+        *active_textbox_id = Some(obj.id);
+    }
+    d.gui_enable();
     current_y += 40.0;
 
+    // Locked nodes show their settings but can't change them.
+    if obj.locked {
+        d.gui_disable();
+    }
+    d.gui_label(Rectangle::new(base_x, current_y, 100.0, 20.0), "Kind:");
+    current_y += 25.0;
+    let mut active_kind = obj.kind as i32;
+    d.gui_toggle_group(
+        Rectangle::new(base_x, current_y, 70.0, 25.0),
+        "Plain;Group;Data;Render;Asset",
+        &mut active_kind,
+    );
+    if focus == Some(PanelControl::Kind) {
+        mark_focus(
+            d,
+            &mut panel,
+            Rectangle::new(base_x, current_y, 70.0 * 5.0, 25.0),
+        );
+        if activated && !obj.locked {
+            active_kind = (active_kind + 1) % 5;
+        }
+    }
+    let kind = match active_kind {
+        0 => NodeKind::Plain,
+        1 => NodeKind::Group,
+        2 => NodeKind::Data,
+        3 => NodeKind::Render,
+        _ => NodeKind::Asset,
+    };
+    if kind != obj.kind {
+        obj.kind = kind;
+        if let Some((shape, color)) = kind.defaults() {
+            obj.shape = shape;
+            obj.color = color;
+        }
+    }
+    current_y += 35.0;
+
     d.gui_label(Rectangle::new(base_x, current_y, 100.0, 20.0), "Shape:");
     current_y += 25.0;
     let mut active_shape = obj.shape as i32;
     d.gui_toggle_group(
         Rectangle::new(base_x, current_y, 70.0, 25.0),
-        "Square;Circle;Triangle",
+        "Square;Circle;Triangle;Star;Hexagon",
         &mut active_shape,
     );
+    if focus == Some(PanelControl::Shape) {
+        mark_focus(
+            d,
+            &mut panel,
+            Rectangle::new(base_x, current_y, 70.0 * 5.0, 25.0),
+        );
+        if activated && !obj.locked {
+            active_shape = (active_shape + 1) % 5;
+        }
+    }
     obj.shape = match active_shape {
         0 => Shape::Square,
         1 => Shape::Circle,
-        _ => Shape::Triangle,
+        2 => Shape::Triangle,
+        3 => Shape::Star,
+        _ => Shape::Hexagon,
     };
     current_y += 35.0;
 
+    d.gui_label(Rectangle::new(base_x, current_y, 60.0, 30.0), "Glyph:");
+    let glyph_bounds = Rectangle::new(base_x + 60.0, current_y, 40.0, 30.0);
+    let inside = check_collision_point_rec(d.get_mouse_position(), glyph_bounds);
+    let editing = *panel_field == PanelField::Glyph;
+    let mut glyph = obj.glyph.map(String::from).unwrap_or_default();
+    let entered = gui_text_line(d, glyph_bounds, &mut glyph, editing);
+    // Typing replaces the glyph with the last character; clearing it brings the shape back.
+    obj.glyph = glyph.chars().last();
+    if editing
+        && (entered
+            || d.is_key_pressed(KeyboardKey::KEY_ESCAPE)
+            || (clicked && !inside)
+            || obj.locked)
+    {
+        *panel_field = PanelField::Name;
+        *active_textbox_id = None;
+    } else if clicked && inside && !obj.locked {
+        *panel_field = PanelField::Glyph;
+        *active_textbox_id = Some(obj.id);
+    }
+    if focus == Some(PanelControl::Glyph) {
+        mark_focus(d, &mut panel, glyph_bounds);
+        if activated && !obj.locked {
+            *panel_field = PanelField::Glyph;
+            *active_textbox_id = Some(obj.id);
+        }
+    }
+    d.gui_label(
+        Rectangle::new(base_x + 110.0, current_y, window_rect.width - 130.0, 30.0),
+        "Drawn instead of the shape",
+    );
+    current_y += 40.0;
+
     d.gui_label(
         Rectangle::new(base_x, current_y, 100.0, 20.0),
         "Rotation Speed:",
     );
     current_y += 25.0;
+    let speed_bounds = Rectangle::new(base_x, current_y, window_rect.width - 20.0, 20.0);
     d.gui_slider_bar(
-        Rectangle::new(base_x, current_y, window_rect.width - 20.0, 20.0),
+        speed_bounds,
         "",
         &format!("{:.0}", obj.rotation_speed),
         &mut obj.rotation_speed,
         -180.0,
         180.0,
     );
+    if focus == Some(PanelControl::RotationSpeed) {
+        mark_focus(d, &mut panel, speed_bounds);
+        if !obj.locked {
+            obj.rotation_speed = (obj.rotation_speed + step * 10.0).clamp(-180.0, 180.0);
+        }
+    }
+    current_y += 30.0;
+
+    d.gui_label(
+        Rectangle::new(base_x, current_y, 120.0, 20.0),
+        "Rotation Offset:",
+    );
+    current_y += 25.0;
+    let offset_bounds = Rectangle::new(base_x, current_y, window_rect.width - 20.0, 20.0);
+    d.gui_slider_bar(
+        offset_bounds,
+        "",
+        &format!("{:.0}", obj.rotation_offset),
+        &mut obj.rotation_offset,
+        -180.0,
+        180.0,
+    );
+    if focus == Some(PanelControl::RotationOffset) {
+        mark_focus(d, &mut panel, offset_bounds);
+        if !obj.locked {
+            obj.rotation_offset = (obj.rotation_offset + step * 10.0).clamp(-180.0, 180.0);
+        }
+    }
+    current_y += 30.0;
+
+    d.gui_label(Rectangle::new(base_x, current_y, 100.0, 20.0), "Scale:");
+    current_y += 25.0;
+    let scale_bounds = Rectangle::new(base_x, current_y, window_rect.width - 20.0, 20.0);
+    d.gui_slider_bar(
+        scale_bounds,
+        "",
+        &format!("{:.2}x", obj.scale),
+        &mut obj.scale,
+        MIN_NODE_SCALE,
+        MAX_NODE_SCALE,
+    );
+    if focus == Some(PanelControl::Scale) {
+        mark_focus(d, &mut panel, scale_bounds);
+        if !obj.locked {
+            obj.scale = (obj.scale + step * 0.1).clamp(MIN_NODE_SCALE, MAX_NODE_SCALE);
+        }
+    }
     current_y += 30.0;
 
     d.gui_label(Rectangle::new(base_x, current_y, 100.0, 20.0), "Color:");
+    let color_label_y = current_y;
     current_y += 25.0;
-    obj.color = d.gui_color_picker(
+    // Depth colouring overrides the stored colour, so the picker is shown but inert.
+    if color_by_depth {
+        d.gui_disable();
+    }
+    let cell = (window_rect.width - 20.0) / COLOR_SWATCHES.len() as f32;
+    for (i, swatch) in COLOR_SWATCHES.into_iter().enumerate() {
+        let bounds = Rectangle::new(base_x + i as f32 * cell, current_y, cell - 3.0, 24.0);
+        if d.gui_button(bounds, "") && !color_by_depth && !obj.locked {
+            obj.color = swatch;
+        }
+        let inset = Rectangle::new(
+            bounds.x + 3.0,
+            bounds.y + 3.0,
+            bounds.width - 6.0,
+            bounds.height - 6.0,
+        );
+        d.draw_rectangle_rec(inset, swatch);
+    }
+    if focus == Some(PanelControl::Color) {
+        mark_focus(
+            d,
+            &mut panel,
+            Rectangle::new(
+                base_x,
+                current_y,
+                cell * COLOR_SWATCHES.len() as f32 - 3.0,
+                24.0,
+            ),
+        );
+        // Steps to the swatch after the current colour, or the first one.
+        if activated && !color_by_depth && !obj.locked {
+            let next = COLOR_SWATCHES
+                .iter()
+                .position(|swatch| *swatch == obj.color)
+                .map_or(0, |i| (i + 1) % COLOR_SWATCHES.len());
+            obj.color = COLOR_SWATCHES[next];
+        }
+    }
+    current_y += 30.0;
+    let picked = d.gui_color_picker(
         Rectangle::new(base_x, current_y, window_rect.width - 20.0, 150.0),
         "",
         obj.color,
     );
+    if !color_by_depth && !obj.locked {
+        obj.color = picked;
+    }
+    if obj.children.is_empty() {
+        d.gui_disable();
+    }
+    let apply_bounds = Rectangle::new(
+        window_rect.x + window_rect.width - 190.0,
+        color_label_y - 2.0,
+        180.0,
+        24.0,
+    );
+    let apply_focused = focus == Some(PanelControl::ApplyColor);
+    if apply_focused {
+        mark_focus(d, &mut panel, apply_bounds);
+    }
+    if (d.gui_button(apply_bounds, "Apply color to children")
+        || (apply_focused && activated && !obj.children.is_empty()))
+        && !color_by_depth
+        && !obj.locked
+    {
+        set_color_recursive(obj, obj.color);
+    }
+    d.gui_enable();
     current_y += 160.0;
 
-    if d.gui_button(Rectangle::new(base_x, current_y, 150.0, 30.0), "Add Child") {
-        requests.push(EditorRequest::AddChild { parent_id: obj.id });
-    }
-    if d.gui_button(
-        Rectangle::new(
-            window_rect.x + window_rect.width - 160.0,
-            current_y,
-            150.0,
-            30.0,
-        ),
-        "Delete Node",
-    ) {
-        requests.push(EditorRequest::DeleteNode { node_id: obj.id });
-        *active_settings_id = None;
+    if obj.locked {
+        d.gui_disable();
+    }
+    let mut gradient = obj.color2.is_some();
+    let gradient_bounds = Rectangle::new(base_x, current_y, 20.0, 20.0);
+    d.gui_check_box(gradient_bounds, "Gradient to:", &mut gradient);
+    if focus == Some(PanelControl::Gradient) {
+        mark_focus(d, &mut panel, gradient_bounds);
+        gradient ^= activated;
+    }
+    let picked = d.gui_color_picker(
+        Rectangle::new(base_x + 150.0, current_y, window_rect.width - 190.0, 60.0),
+        "",
+        obj.color2.unwrap_or(Color::BLACK),
+    );
+    if !obj.locked {
+        obj.color2 = gradient.then_some(picked);
+    }
+    d.gui_enable();
+    current_y += 70.0;
+
+    if obj.locked {
+        d.gui_disable();
+    }
+    d.gui_label(Rectangle::new(base_x, current_y, 100.0, 20.0), "Opacity:");
+    current_y += 25.0;
+    let mut alpha = obj.alpha as f32;
+    let alpha_bounds = Rectangle::new(base_x, current_y, window_rect.width - 20.0, 20.0);
+    d.gui_slider_bar(
+        alpha_bounds,
+        "",
+        &format!("{}", obj.alpha),
+        &mut alpha,
+        0.0,
+        255.0,
+    );
+    if focus == Some(PanelControl::Opacity) {
+        mark_focus(d, &mut panel, alpha_bounds);
+        alpha = (alpha + step * 15.0).clamp(0.0, 255.0);
+    }
+    if !obj.locked {
+        obj.alpha = alpha.round() as u8;
+    }
+    current_y += 30.0;
+
+    let mut outlined = obj.outline.is_some();
+    let outline_bounds = Rectangle::new(base_x, current_y, 20.0, 20.0);
+    d.gui_check_box(outline_bounds, "Outline", &mut outlined);
+    if focus == Some(PanelControl::Outline) {
+        mark_focus(d, &mut panel, outline_bounds);
+        outlined ^= activated;
+    }
+    let mut thickness = obj.outline_thickness;
+    d.gui_slider_bar(
+        Rectangle::new(base_x + 150.0, current_y, window_rect.width - 200.0, 20.0),
+        "",
+        &format!("{:.0}", obj.outline_thickness),
+        &mut thickness,
+        1.0,
+        10.0,
+    );
+    current_y += 30.0;
+    let picked = d.gui_color_picker(
+        Rectangle::new(base_x, current_y, window_rect.width - 50.0, 60.0),
+        "",
+        obj.outline.unwrap_or(Color::WHITE),
+    );
+    if !obj.locked {
+        obj.outline = outlined.then_some(picked);
+        obj.outline_thickness = thickness;
+    }
+    d.gui_enable();
+    current_y += 70.0;
+
+    let visible_bounds = Rectangle::new(base_x, current_y, 20.0, 20.0);
+    d.gui_check_box(visible_bounds, "Visible", &mut obj.visible);
+    if focus == Some(PanelControl::Visible) {
+        mark_focus(d, &mut panel, visible_bounds);
+        obj.visible ^= activated;
+    }
+    let locked_bounds = Rectangle::new(base_x + 150.0, current_y, 20.0, 20.0);
+    d.gui_check_box(locked_bounds, "Locked", &mut obj.locked);
+    if focus == Some(PanelControl::Locked) {
+        mark_focus(d, &mut panel, locked_bounds);
+        obj.locked ^= activated;
+    }
+    if obj.locked && *active_textbox_id == Some(obj.id) {
+        obj.text = obj.text_buffer.clone();
+        *active_textbox_id = None;
+    }
+    current_y += 30.0;
+
+    let add_bounds = Rectangle::new(base_x, current_y, 150.0, 30.0);
+    let add_focused = focus == Some(PanelControl::AddChild);
+    if add_focused {
+        mark_focus(d, &mut panel, add_bounds);
+    }
+    if d.gui_button(add_bounds, "Add Child") || (add_focused && activated) {
+        requests.push(EditorRequest::AddChild {
+            parent_id: obj.id,
+            select: false,
+        });
+    }
+    if obj.locked {
+        d.gui_disable();
+    }
+    let delete_bounds = Rectangle::new(
+        window_rect.x + window_rect.width - 160.0,
+        current_y,
+        150.0,
+        30.0,
+    );
+    let delete_focused = focus == Some(PanelControl::Delete);
+    if delete_focused {
+        mark_focus(d, &mut panel, delete_bounds);
+    }
+    if d.gui_button(delete_bounds, "Delete Node") || (delete_focused && activated && !obj.locked) {
+        requests.push(EditorRequest::DeleteNodes {
+            node_ids: vec![obj.id],
+        });
+    }
+    d.gui_enable();
+    current_y += 40.0;
+
+    // Pinning bakes the computed position into `position`; unpinning hands the node
+    // back to the automatic layout.
+    if obj.locked {
+        d.gui_disable();
+    }
+    let pinned = obj.position.is_some();
+    let pin_bounds = Rectangle::new(
+        window_rect.x + window_rect.width - 160.0,
+        current_y,
+        150.0,
+        30.0,
+    );
+    let pin_focused = focus == Some(PanelControl::Pin);
+    if pin_focused {
+        mark_focus(d, &mut panel, pin_bounds);
+    }
+    if d.gui_button(pin_bounds, if pinned { "Unpin" } else { "Pin Position" })
+        || (pin_focused && activated && !obj.locked)
+    {
+        requests.push(if pinned {
+            EditorRequest::ResetPosition { node_id: obj.id }
+        } else {
+            EditorRequest::PinPosition { node_id: obj.id }
+        });
+    }
+    d.gui_enable();
+
+    // Drop the saved file back onto the window to insert a copy.
+    let template_bounds = Rectangle::new(base_x, current_y, 150.0, 30.0);
+    let template_focused = focus == Some(PanelControl::SaveTemplate);
+    if template_focused {
+        mark_focus(d, &mut panel, template_bounds);
+    }
+    if d.gui_button(template_bounds, "Save Template") || (template_focused && activated) {
+        let path = template_path(&obj.text);
+        match export_template(obj, &path) {
+            Ok(()) => println!("Saved template to '{}'", path),
+            Err(err) => println!("Template save failed: {}", err),
+        }
+    }
+    current_y += 40.0;
+
+    if lock_hidden {
+        d.gui_unlock();
+    }
+    panel.content_height = current_y - content_top;
+    panel
+}
+
+/// Processes the request queue to add or delete nodes.
+fn process_editor_requests(state: &mut State) {
+    let requests = state.requests.drain(..).collect::<Vec<_>>();
+    // Every request changes the tree or where its nodes sit.
+    if !requests.is_empty() {
+        state.layout_dirty = true;
+    }
+    for req in requests {
+        match req {
+            EditorRequest::AddRoot => {
+                let new_id = state.new_id();
+                let name = default_node_name(None, new_id);
+                let new_node = SceneObject::new(new_id, &name, Shape::Square, state.theme.node);
+                state.scene_objects.push(new_node);
+            }
+            EditorRequest::AddChild { parent_id, select } => {
+                add_child(state, parent_id, select);
+            }
+            EditorRequest::AddShapedChild {
+                parent_id,
+                shape,
+                text,
+            } => {
+                if let Some(new_id) = add_child(state, parent_id, true)
+                    && let Some(obj) = find_object_by_id_mut(&mut state.scene_objects, new_id)
+                {
+                    obj.shape = shape;
+                    if !text.is_empty() {
+                        obj.text_buffer.clone_from(&text);
+                        obj.text = text;
+                    }
+                }
+            }
+            EditorRequest::DeleteNodes { node_ids } => {
+                let (locked, node_ids): (Vec<u32>, Vec<u32>) = node_ids
+                    .into_iter()
+                    .filter(|id| find_object_by_id(&state.scene_objects, *id).is_some())
+                    .partition(|id| {
+                        find_object_by_id(&state.scene_objects, *id).is_some_and(contains_locked)
+                    });
+                match locked.len() {
+                    0 => {}
+                    1 => set_notice(state, "1 locked node wasn't deleted"),
+                    n => set_notice(state, &format!("{} locked nodes weren't deleted", n)),
+                }
+                let any_children = node_ids.iter().any(|id| {
+                    find_object_by_id(&state.scene_objects, *id)
+                        .is_some_and(|obj| !obj.children.is_empty())
+                });
+                if any_children {
+                    state.pending_confirm = Some(EditorRequest::DeleteNodes { node_ids });
+                } else {
+                    for node_id in node_ids {
+                        delete_subtree(state, node_id);
+                    }
+                }
+            }
+            EditorRequest::DeleteConfirmed { node_ids } => {
+                for node_id in node_ids {
+                    if !find_object_by_id(&state.scene_objects, node_id)
+                        .is_some_and(contains_locked)
+                    {
+                        delete_subtree(state, node_id);
+                    }
+                }
+            }
+            EditorRequest::Paste { parent_id } => {
+                let Some(copy) = state.clipboard.clone() else {
+                    continue;
+                };
+                insert_subtree(state, parent_id, copy);
+            }
+            EditorRequest::Insert { parent_id, subtree } => {
+                insert_subtree(state, parent_id, subtree);
+            }
+            EditorRequest::ReplaceScene { objects } => {
+                // Deleting the old roots clears the editing state that points into them.
+                let roots: Vec<u32> = state.scene_objects.iter().map(|obj| obj.id).collect();
+                for id in roots {
+                    delete_subtree(state, id);
+                }
+                state.context_menu = None;
+                state.dragging = None;
+                state.hover_id = None;
+                state.next_id = all_ids(&objects).into_iter().max().unwrap_or(0);
+                state.scene_objects = objects;
+            }
+            EditorRequest::Duplicate { node_id } => {
+                let Some(mut copy) = find_object_by_id(&state.scene_objects, node_id).cloned()
+                else {
+                    continue;
+                };
+                assign_fresh_ids(&mut copy, state);
+                if let Some((siblings, index)) =
+                    find_siblings_mut(&mut state.scene_objects, node_id)
+                {
+                    siblings.insert(index + 1, copy);
+                }
+            }
+            EditorRequest::ToggleCollapse { node_id } => {
+                if let Some(obj) = find_object_by_id_mut(&mut state.scene_objects, node_id) {
+                    obj.collapsed = !obj.collapsed;
+                }
+            }
+            EditorRequest::SetCollapsedAll { collapsed } => {
+                set_collapsed_all(&mut state.scene_objects, collapsed);
+            }
+            EditorRequest::CollapseToDepth { depth } => {
+                collapse_to_depth(&mut state.scene_objects, depth);
+            }
+            EditorRequest::MoveSibling { node_id, delta } => {
+                move_sibling(&mut state.scene_objects, node_id, delta);
+            }
+            EditorRequest::Align { horizontal } => {
+                // Locked nodes stay where they are, as they do for dragging and pinning.
+                let (locked, picked): (Vec<_>, Vec<(u32, Vector2)>) = state
+                    .selected_ids
+                    .iter()
+                    .filter_map(|id| state.layout_positions.get(id).map(|pos| (*id, *pos)))
+                    .partition(|(id, _)| {
+                        find_object_by_id(&state.scene_objects, *id).is_some_and(|obj| obj.locked)
+                    });
+                match locked.len() {
+                    0 => {}
+                    1 => set_notice(state, "1 locked node wasn't aligned"),
+                    n => set_notice(state, &format!("{} locked nodes weren't aligned", n)),
+                }
+                if picked.len() < 2 {
+                    continue;
+                }
+                let mean = picked
+                    .iter()
+                    .fold(Vector2::zero(), |sum, (_, pos)| sum + *pos)
+                    / picked.len() as f32;
+                for (id, pos) in picked {
+                    if let Some(obj) = find_object_by_id_mut(&mut state.scene_objects, id) {
+                        obj.position = Some(if horizontal {
+                            Vector2::new(pos.x, mean.y)
+                        } else {
+                            Vector2::new(mean.x, pos.y)
+                        });
+                    }
+                }
+            }
+            EditorRequest::ResetPosition { node_id } => {
+                if let Some(obj) = find_object_by_id_mut(&mut state.scene_objects, node_id) {
+                    obj.position = None;
+                }
+            }
+            EditorRequest::PinPosition { node_id } => {
+                if let Some(pos) = state.layout_positions.get(&node_id)
+                    && let Some(obj) = find_object_by_id_mut(&mut state.scene_objects, node_id)
+                {
+                    obj.position = Some(*pos);
+                }
+            }
+            EditorRequest::SetCategoryFilter { category } => {
+                state.filter_category = category;
+            }
+            EditorRequest::Group { node_ids } => {
+                let picked: Vec<u32> = all_ids(&state.scene_objects)
+                    .into_iter()
+                    .filter(|id| node_ids.contains(id))
+                    .collect();
+                let Some(&first) = picked.first() else {
+                    continue;
+                };
+                let nested = picked.iter().any(|id| {
+                    path_to_node(&state.scene_objects, *id).is_some_and(|path| {
+                        path[..path.len() - 1]
+                            .iter()
+                            .any(|ancestor| picked.contains(ancestor))
+                    })
+                });
+                if nested {
+                    continue;
+                }
+                let parent_id = path_to_node(&state.scene_objects, first)
+                    .and_then(|path| path.iter().rev().nth(1).copied());
+                let Some((_, index)) = find_siblings_mut(&mut state.scene_objects, first) else {
+                    continue;
+                };
+
+                let new_id = state.new_id();
+                let name = format!("Group {}", new_id);
+                let mut group = SceneObject::new(new_id, &name, Shape::Square, state.theme.node);
+                for id in &picked {
+                    if let Some((siblings, i)) = find_siblings_mut(&mut state.scene_objects, *id) {
+                        group.children.push(siblings.remove(i));
+                    }
+                }
+                // The rest of the nodes come after the first, so its index still holds.
+                let siblings = match parent_id {
+                    Some(parent_id) => {
+                        match find_object_by_id_mut(&mut state.scene_objects, parent_id) {
+                            Some(parent) => &mut parent.children,
+                            None => continue,
+                        }
+                    }
+                    None => &mut state.scene_objects,
+                };
+                siblings.insert(index.min(siblings.len()), group);
+                state.selected_ids.clear();
+                state.focus_pending = Some(new_id);
+            }
+            EditorRequest::Ungroup { node_id } => {
+                if find_object_by_id(&state.scene_objects, node_id)
+                    .is_none_or(|obj| obj.locked || obj.children.is_empty())
+                {
+                    continue;
+                }
+                if let Some((siblings, index)) =
+                    find_siblings_mut(&mut state.scene_objects, node_id)
+                {
+                    let group = siblings.remove(index);
+                    siblings.splice(index..index, group.children);
+                }
+                if state.active_settings_id == Some(node_id) {
+                    state.active_settings_id = None;
+                }
+                state.selected_ids.remove(&node_id);
+            }
+        }
+    }
+}
+
+// --- Helper Functions ---
+
+/// Shows `text` at the bottom of the screen for a few seconds.
+fn set_notice(state: &mut State, text: &str) {
+    state.notice = Some((text.to_string(), Instant::now()));
+}
+
+/// Removes `node_id` and its subtree. Editing and selection state that points into the
+/// subtree is cleared, so no text box or panel is left on a node that's gone.
+fn delete_subtree(state: &mut State, node_id: u32) {
+    let Some(obj) = find_object_by_id(&state.scene_objects, node_id) else {
+        return;
+    };
+    let ids: HashSet<u32> = all_ids(std::slice::from_ref(obj)).into_iter().collect();
+    find_and_delete_node(&mut state.scene_objects, node_id);
+    if state.active_textbox_id.is_some_and(|id| ids.contains(&id)) {
+        state.active_textbox_id = None;
+        state.panel_field = PanelField::Name;
+    }
+    for editing in [&mut state.active_settings_id, &mut state.inline_edit_id] {
+        if editing.is_some_and(|id| ids.contains(&id)) {
+            *editing = None;
+        }
+    }
+    state.selected_ids.retain(|id| !ids.contains(id));
+}
+
+/// Name for a node created by the editor: "Node <id>" for roots, or "<parent> child K"
+/// where K is its position among the parent's children once added.
+fn default_node_name(parent: Option<&SceneObject>, id: u32) -> String {
+    match parent {
+        Some(parent) => format!("{} child {}", parent.text, parent.children.len() + 1),
+        None => format!("Node {}", id),
     }
 }
 
-/// Processes the request queue to add or delete nodes.
-fn process_editor_requests(state: &mut State) {
-    let requests = state.requests.drain(..).collect::<Vec<_>>();
-    for req in requests {
-        match req {
-            EditorRequest::AddChild { parent_id } => {
-                let new_id = state.new_id();
-                if let Some(parent) = find_object_by_id_mut(&mut state.scene_objects, parent_id) {
-                    let new_node =
-                        SceneObject::new(new_id, "New Node", Shape::Square, Color::WHITE);
-                    parent.children.push(new_node);
-                }
-            }
-            EditorRequest::DeleteNode { node_id } => {
-                find_and_delete_node(&mut state.scene_objects, node_id);
+/// Gives `subtree` fresh ids and attaches it under `parent_id`, or at the root level.
+fn insert_subtree(state: &mut State, parent_id: Option<u32>, mut subtree: SceneObject) {
+    assign_fresh_ids(&mut subtree, state);
+    match parent_id {
+        Some(parent_id) => {
+            if let Some(parent) = find_object_by_id_mut(&mut state.scene_objects, parent_id) {
+                parent.children.push(subtree);
             }
         }
+        None => state.scene_objects.push(subtree),
     }
 }
 
-// --- Helper Functions ---
+/// Appends a default child to `parent_id`, opening the parent and focusing the child
+/// with `select`. Returns the child's id, or None if the parent is gone.
+fn add_child(state: &mut State, parent_id: u32, select: bool) -> Option<u32> {
+    let new_id = state.new_id();
+    let parent = find_object_by_id_mut(&mut state.scene_objects, parent_id)?;
+    let name = default_node_name(Some(parent), new_id);
+    let new_node = SceneObject::new(new_id, &name, Shape::Square, state.theme.node);
+    parent.children.push(new_node);
+    if select {
+        parent.collapsed = false;
+        state.focus_pending = Some(new_id);
+    }
+    Some(new_id)
+}
+
+fn assign_fresh_ids(obj: &mut SceneObject, state: &mut State) {
+    obj.id = state.new_id();
+    for child in &mut obj.children {
+        assign_fresh_ids(child, state);
+    }
+}
 
 /// A 100% safe wrapper around `gui_text_box` that handles buffer management correctly.
 /// Returns `true` if the user pressed Enter.
 fn gui_text_box_safe(d: &mut RaylibDrawHandle, bounds: Rectangle, text: &mut String) -> bool {
     let mut result = false;
 
-    // Check if the text box should be active.
-    if check_collision_point_rec(d.get_mouse_position(), bounds) {
+    // Check if the text box should be active. A locked GUI (modal dialog) takes no typing.
+    if !d.gui_is_locked() && check_collision_point_rec(d.get_mouse_position(), bounds) {
         // Process character input
         while let Some(key) = d.get_char_pressed() {
             text.push(key);
@@ -468,6 +4774,394 @@ fn gui_text_box_safe(d: &mut RaylibDrawHandle, bounds: Rectangle, text: &mut Str
     result
 }
 
+/// A single-line text box drawn like `gui_text_area`. Returns `true` if Enter was pressed.
+fn gui_text_line(
+    d: &mut RaylibDrawHandle,
+    bounds: Rectangle,
+    text: &mut String,
+    focused: bool,
+) -> bool {
+    const FONT_SIZE: i32 = 10;
+
+    let mut entered = false;
+    if focused {
+        while let Some(key) = d.get_char_pressed() {
+            text.push(key);
+        }
+        entered = d.is_key_pressed(KeyboardKey::KEY_ENTER);
+        if d.is_key_pressed_repeat(KeyboardKey::KEY_BACKSPACE)
+            || d.is_key_pressed(KeyboardKey::KEY_BACKSPACE)
+        {
+            text.pop();
+        }
+    }
+
+    let border = if focused { Color::SKYBLUE } else { Color::GRAY };
+    d.draw_rectangle_rec(bounds, Color::RAYWHITE);
+    d.draw_rectangle_lines_ex(bounds, 1.0, border);
+    // raylib can't nest scissor areas and the settings panel already clips, so the text
+    // is cut to fit instead, keeping the end in view while typing.
+    let mut shown = text.as_str();
+    while d.measure_text(shown, FONT_SIZE) > bounds.width as i32 - 8 {
+        let mut chars = shown.chars();
+        chars.next();
+        shown = chars.as_str();
+    }
+    d.draw_text(
+        shown,
+        bounds.x as i32 + 4,
+        (bounds.y + (bounds.height - FONT_SIZE as f32) / 2.0) as i32,
+        FONT_SIZE,
+        Color::DARKGRAY,
+    );
+    entered
+}
+
+/// A multi-line text box. Enter inserts a newline and long lines wrap at word
+/// boundaries; lines that don't fit above the bottom edge aren't drawn.
+fn gui_text_area(d: &mut RaylibDrawHandle, bounds: Rectangle, text: &mut String, focused: bool) {
+    const FONT_SIZE: i32 = 10;
+    const LINE_HEIGHT: i32 = 12;
+
+    if focused {
+        while let Some(key) = d.get_char_pressed() {
+            text.push(key);
+        }
+        if d.is_key_pressed(KeyboardKey::KEY_ENTER) {
+            text.push('\n');
+        }
+        if d.is_key_pressed_repeat(KeyboardKey::KEY_BACKSPACE)
+            || d.is_key_pressed(KeyboardKey::KEY_BACKSPACE)
+        {
+            text.pop();
+        }
+    }
+
+    let border = if focused { Color::SKYBLUE } else { Color::GRAY };
+    d.draw_rectangle_rec(bounds, Color::RAYWHITE);
+    d.draw_rectangle_lines_ex(bounds, 1.0, border);
+
+    let inner_width = bounds.width as i32 - 8;
+    let lines = wrap_text(d, text, inner_width, FONT_SIZE);
+    // Not a scissor area, for the same reason as in `gui_text_line`.
+    let max_lines = ((bounds.height as i32 - 4) / LINE_HEIGHT).max(0) as usize;
+    for (i, line) in lines.iter().take(max_lines).enumerate() {
+        d.draw_text(
+            line,
+            bounds.x as i32 + 4,
+            bounds.y as i32 + 4 + i as i32 * LINE_HEIGHT,
+            FONT_SIZE,
+            Color::DARKGRAY,
+        );
+    }
+}
+
+/// Splits `text` on newlines, then greedily on spaces so no line is wider than `width`.
+/// A single word wider than `width` gets a line of its own.
+fn wrap_text(rl: &RaylibHandle, text: &str, width: i32, font_size: i32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        for word in paragraph.split(' ') {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", line, word)
+            };
+            if !line.is_empty() && rl.measure_text(&candidate, font_size) > width {
+                lines.push(std::mem::replace(&mut line, word.to_string()));
+            } else {
+                line = candidate;
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Draws one parent-to-child edge in the given style, leaving the parent along the
+/// axis `direction` grows the tree in.
+fn draw_connector(
+    d: &mut impl RaylibDraw,
+    style: ConnectorStyle,
+    direction: LayoutDirection,
+    from: Vector2,
+    to: Vector2,
+    thick: f32,
+    color: Color,
+) {
+    match style {
+        ConnectorStyle::Straight => d.draw_line_ex(from, to, thick, color),
+        ConnectorStyle::Elbow => {
+            for pair in connector_points(style, direction, from, to).windows(2) {
+                d.draw_line_ex(pair[0], pair[1], thick, color);
+            }
+        }
+        ConnectorStyle::Bezier => {
+            let (c1, c2) = bezier_controls(direction, from, to);
+            d.draw_spline_segment_bezier_cubic(from, c1, c2, to, thick, color);
+        }
+    }
+}
+
+/// Whether elbows and curves leave the parent vertically: always in a top-down tree,
+/// never in a left-right one. Radial edges point every way, so each follows whichever
+/// axis it spans more of.
+fn connector_is_vertical(direction: LayoutDirection, from: Vector2, to: Vector2) -> bool {
+    match direction {
+        LayoutDirection::LeftRight => false,
+        LayoutDirection::TopDown => true,
+        LayoutDirection::Radial => (to.y - from.y).abs() > (to.x - from.x).abs(),
+    }
+}
+
+/// Inner control points of a Bezier connector, halfway along the growth axis, so the
+/// curve leaves the parent and meets the child along it.
+fn bezier_controls(direction: LayoutDirection, from: Vector2, to: Vector2) -> (Vector2, Vector2) {
+    if connector_is_vertical(direction, from, to) {
+        let mid_y = (from.y + to.y) / 2.0;
+        (Vector2::new(from.x, mid_y), Vector2::new(to.x, mid_y))
+    } else {
+        let mid_x = (from.x + to.x) / 2.0;
+        (Vector2::new(mid_x, from.y), Vector2::new(mid_x, to.y))
+    }
+}
+
+/// The path `draw_connector` draws, as a polyline for hit testing. Bezier curves are
+/// sampled from the same cubic `draw_spline_segment_bezier_cubic` draws.
+fn connector_points(
+    style: ConnectorStyle,
+    direction: LayoutDirection,
+    from: Vector2,
+    to: Vector2,
+) -> Vec<Vector2> {
+    match style {
+        ConnectorStyle::Straight => vec![from, to],
+        ConnectorStyle::Elbow if connector_is_vertical(direction, from, to) => {
+            let mid_y = (from.y + to.y) / 2.0;
+            vec![
+                from,
+                Vector2::new(from.x, mid_y),
+                Vector2::new(to.x, mid_y),
+                to,
+            ]
+        }
+        ConnectorStyle::Elbow => {
+            let mid_x = (from.x + to.x) / 2.0;
+            vec![
+                from,
+                Vector2::new(mid_x, from.y),
+                Vector2::new(mid_x, to.y),
+                to,
+            ]
+        }
+        ConnectorStyle::Bezier => {
+            const SEGMENTS: usize = 24;
+            let (c1, c2) = bezier_controls(direction, from, to);
+            (0..=SEGMENTS)
+                .map(|i| {
+                    let t = i as f32 / SEGMENTS as f32;
+                    let u = 1.0 - t;
+                    from * (u * u * u)
+                        + c1 * (3.0 * u * u * t)
+                        + c2 * (3.0 * u * t * t)
+                        + to * (t * t * t)
+                })
+                .collect()
+        }
+    }
+}
+
+/// A small padlock whose body is centred on `pos`.
+fn draw_lock_icon(d: &mut impl RaylibDraw, pos: Vector2, color: Color) {
+    d.draw_ring(
+        pos - Vector2::new(0.0, 4.0),
+        3.0,
+        4.5,
+        180.0,
+        360.0,
+        8,
+        color,
+    );
+    d.draw_rectangle_v(pos - Vector2::new(5.0, 4.0), Vector2::new(10.0, 8.0), color);
+}
+
+/// Draws `glyph` centred on `pos` at font size `size`, rotated by `angle` degrees.
+fn draw_glyph(
+    d: &mut impl RaylibDraw,
+    font: &WeakFont,
+    glyph: char,
+    pos: Vector2,
+    size: f32,
+    angle: f32,
+    color: Color,
+) {
+    let text = glyph.to_string();
+    let extent = font.measure_text(&text, size, 0.0);
+    d.draw_text_pro(font, &text, pos, extent / 2.0, angle, size, 0.0, color);
+}
+
+/// Fills a node shape of side (or diameter) `size`, rotated by `angle` degrees.
+fn draw_shape(
+    d: &mut impl RaylibDraw,
+    shape: Shape,
+    pos: Vector2,
+    size: f32,
+    angle: f32,
+    fill: Color,
+) {
+    match shape {
+        Shape::Square => d.draw_rectangle_pro(
+            Rectangle::new(pos.x, pos.y, size, size),
+            Vector2::new(size / 2.0, size / 2.0),
+            angle,
+            fill,
+        ),
+        Shape::Circle => d.draw_circle_v(pos, size / 2.0, fill),
+        Shape::Triangle => {
+            let [p1, p2, p3] = triangle_vertices(pos, size, angle);
+            d.draw_triangle(p1, p2, p3, fill);
+        }
+        Shape::Star => {
+            let points = star_vertices(pos, size, angle);
+            for i in 0..points.len() {
+                let next = points[(i + 1) % points.len()];
+                d.draw_triangle(pos, next, points[i], fill);
+            }
+        }
+        Shape::Hexagon => d.draw_poly(pos, 6, size / 2.0, angle, fill),
+    }
+}
+
+/// Fills `obj`'s shape blending from `from` to `to`. Squares get a true top-to-bottom
+/// gradient when upright and thin bands in their own frame when rotated. The other
+/// shapes can't be split into strips, so they blend inwards from the edge instead.
+fn draw_gradient_fill(
+    d: &mut impl RaylibDraw,
+    obj: &SceneObject,
+    pos: Vector2,
+    from: Color,
+    to: Color,
+) {
+    const STEPS: usize = 12;
+    let size = obj.size();
+    let angle = obj.angle();
+    match obj.shape {
+        Shape::Square if angle.rem_euclid(360.0) == 0.0 => {
+            d.draw_rectangle_gradient_v(
+                (pos.x - size / 2.0) as i32,
+                (pos.y - size / 2.0) as i32,
+                size as i32,
+                size as i32,
+                from,
+                to,
+            );
+        }
+        Shape::Square => {
+            let band = size / STEPS as f32;
+            for i in 0..STEPS {
+                let t = i as f32 / (STEPS - 1) as f32;
+                // Each band pivots on the node centre, so they rotate together.
+                d.draw_rectangle_pro(
+                    Rectangle::new(pos.x, pos.y, size, band),
+                    Vector2::new(size / 2.0, size / 2.0 - i as f32 * band),
+                    angle,
+                    from.lerp(to, t),
+                );
+            }
+        }
+        shape => {
+            for i in 0..STEPS {
+                let t = i as f32 / STEPS as f32;
+                let scale = 1.0 - t;
+                draw_shape(d, shape, pos, size * scale, angle, from.lerp(to, t));
+            }
+        }
+    }
+}
+
+/// Traces the edge of `obj`'s shape at `pos` with its outline thickness.
+fn draw_outline(d: &mut impl RaylibDraw, obj: &SceneObject, pos: Vector2, color: Color) {
+    let size = obj.size();
+    let thick = obj.outline_thickness;
+    let rotation = obj.angle();
+    match obj.shape {
+        _ if obj.glyph.is_some() => {
+            d.draw_ring(pos, size / 2.0, size / 2.0 + thick, 0.0, 360.0, 48, color)
+        }
+        Shape::Square => {
+            let half = size / 2.0;
+            let corners = [
+                Vector2::new(-half, -half),
+                Vector2::new(half, -half),
+                Vector2::new(half, half),
+                Vector2::new(-half, half),
+            ]
+            .map(|p| rotate_point(p, rotation) + pos);
+            draw_closed_polyline(d, &corners, thick, color);
+        }
+        Shape::Circle => d.draw_ring(pos, size / 2.0, size / 2.0 + thick, 0.0, 360.0, 48, color),
+        Shape::Triangle => {
+            draw_closed_polyline(d, &triangle_vertices(pos, size, rotation), thick, color);
+        }
+        Shape::Star => draw_closed_polyline(d, &star_vertices(pos, size, rotation), thick, color),
+        Shape::Hexagon => d.draw_poly_lines_ex(pos, 6, size / 2.0, rotation, thick, color),
+    }
+}
+
+fn draw_closed_polyline(d: &mut impl RaylibDraw, points: &[Vector2], thick: f32, color: Color) {
+    for (i, point) in points.iter().enumerate() {
+        let next = points[(i + 1) % points.len()];
+        d.draw_line_ex(*point, next, thick, color);
+        // Round the joints so corners don't show notches.
+        d.draw_circle_v(*point, thick / 2.0, color);
+    }
+}
+
+/// Draws a circle outline as alternating dashes.
+fn draw_dashed_circle(d: &mut impl RaylibDraw, center: Vector2, radius: f32, color: Color) {
+    const DASHES: usize = 12;
+    let step = std::f32::consts::TAU / (DASHES * 2) as f32;
+    for i in 0..DASHES {
+        let a0 = (i * 2) as f32 * step;
+        let a1 = a0 + step;
+        let p0 = center + Vector2::new(a0.cos(), a0.sin()) * radius;
+        let p1 = center + Vector2::new(a1.cos(), a1.sin()) * radius;
+        d.draw_line_v(p0, p1, color);
+    }
+}
+
+/// Counts over the whole scene, including hidden and collapsed nodes.
+struct TreeStats {
+    node_count: usize,
+    // Number of levels; a lone root has depth 1.
+    max_depth: usize,
+    leaf_count: usize,
+}
+
+fn tree_stats(objects: &[SceneObject]) -> TreeStats {
+    let mut stats = TreeStats {
+        node_count: 0,
+        max_depth: 0,
+        leaf_count: 0,
+    };
+    for obj in objects {
+        tree_stats_recursive(obj, 1, &mut stats);
+    }
+    stats
+}
+
+fn tree_stats_recursive(obj: &SceneObject, depth: usize, stats: &mut TreeStats) {
+    stats.node_count += 1;
+    stats.max_depth = stats.max_depth.max(depth);
+    if obj.children.is_empty() {
+        stats.leaf_count += 1;
+    }
+    for child in &obj.children {
+        tree_stats_recursive(child, depth + 1, stats);
+    }
+}
+
 fn check_collision_point_rec(point: Vector2, rec: Rectangle) -> bool {
     point.x >= rec.x
         && point.x < (rec.x + rec.width)
@@ -488,10 +5182,84 @@ fn find_and_delete_node(objects: &mut Vec<SceneObject>, id_to_delete: u32) -> bo
     false
 }
 
-fn find_object_by_id_mut<'a>(
-    objects: &'a mut [SceneObject],
+/// Finds the list that directly contains `id`, along with the node's index in it.
+fn find_siblings_mut(
+    objects: &mut Vec<SceneObject>,
     id: u32,
-) -> Option<&'a mut SceneObject> {
+) -> Option<(&mut Vec<SceneObject>, usize)> {
+    if let Some(index) = objects.iter().position(|o| o.id == id) {
+        return Some((objects, index));
+    }
+    for obj in objects {
+        if let Some(found) = find_siblings_mut(&mut obj.children, id) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+/// Ids of nodes whose category matches `category` (ignoring case and surrounding
+/// spaces), plus all of their ancestors so the path to each match stays readable.
+fn category_filter_ids(objects: &[SceneObject], category: &str) -> HashSet<u32> {
+    fn collect(obj: &SceneObject, category: &str, kept: &mut HashSet<u32>) -> bool {
+        let mut keep = obj.category.trim().eq_ignore_ascii_case(category);
+        for child in &obj.children {
+            keep |= collect(child, category, kept);
+        }
+        if keep {
+            kept.insert(obj.id);
+        }
+        keep
+    }
+    let mut kept = HashSet::new();
+    for obj in objects {
+        collect(obj, category.trim(), &mut kept);
+    }
+    kept
+}
+
+/// Gives `obj` and every unlocked descendant `color`. Locked subtrees keep theirs.
+fn set_color_recursive(obj: &mut SceneObject, color: Color) {
+    obj.color = color;
+    for child in obj.children.iter_mut().filter(|c| !c.locked) {
+        set_color_recursive(child, color);
+    }
+}
+
+fn set_collapsed_all(objects: &mut [SceneObject], collapsed: bool) {
+    for obj in objects {
+        obj.collapsed = collapsed;
+        set_collapsed_all(&mut obj.children, collapsed);
+    }
+}
+
+/// Expands nodes above `depth` (roots are depth 1) and collapses the ones at it, so
+/// exactly `depth` levels show. Nodes below keep their flag for when they're revealed.
+fn collapse_to_depth(objects: &mut [SceneObject], depth: usize) {
+    for obj in objects {
+        obj.collapsed = depth <= 1;
+        if depth > 1 {
+            collapse_to_depth(&mut obj.children, depth - 1);
+        }
+    }
+}
+
+/// Moves a node `delta` places within its parent's children (or the roots), clamped
+/// to the ends of the list.
+fn move_sibling(objects: &mut Vec<SceneObject>, id: u32, delta: i32) {
+    if let Some((siblings, index)) = find_siblings_mut(objects, id) {
+        let target = (index as i32 + delta).clamp(0, siblings.len() as i32 - 1) as usize;
+        let node = siblings.remove(index);
+        siblings.insert(target, node);
+    }
+}
+
+/// True if `obj` or anything below it is locked.
+fn contains_locked(obj: &SceneObject) -> bool {
+    obj.locked || obj.children.iter().any(contains_locked)
+}
+
+fn find_object_by_id_mut(objects: &mut [SceneObject], id: u32) -> Option<&mut SceneObject> {
     for obj in objects {
         if obj.id == id {
             return Some(obj);
@@ -503,26 +5271,319 @@ fn find_object_by_id_mut<'a>(
     None
 }
 
-fn find_clicked_object(
-    obj: &SceneObject,
+fn find_object_by_id(objects: &[SceneObject], id: u32) -> Option<&SceneObject> {
+    for obj in objects {
+        if obj.id == id {
+            return Some(obj);
+        }
+        if let Some(found) = find_object_by_id(&obj.children, id) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+/// Ids of nodes whose lowercased name contains `query`, which should be lowercase.
+fn matching_ids(objects: &[SceneObject], query: &str) -> HashSet<u32> {
+    let mut ids = HashSet::new();
+    for obj in objects {
+        if obj.text.to_lowercase().contains(query) {
+            ids.insert(obj.id);
+        }
+        ids.extend(matching_ids(&obj.children, query));
+    }
+    ids
+}
+
+/// Every id in the tree, parents before their children.
+fn all_ids(objects: &[SceneObject]) -> Vec<u32> {
+    let mut ids = Vec::new();
+    collect_ids(objects, &mut ids);
+    ids
+}
+
+/// Gives every node that shares an id with one earlier in tree order an id no other
+/// node has, counting up from the largest. Returns how many nodes changed.
+fn reassign_duplicate_ids(objects: &mut [SceneObject]) -> usize {
+    fn walk(
+        objects: &mut [SceneObject],
+        seen: &mut HashSet<u32>,
+        taken: &mut HashSet<u32>,
+        next: &mut u32,
+    ) -> usize {
+        let mut changed = 0;
+        for obj in objects {
+            if !seen.insert(obj.id) {
+                while taken.contains(next) {
+                    *next = next.wrapping_add(1);
+                }
+                obj.id = *next;
+                taken.insert(obj.id);
+                seen.insert(obj.id);
+                changed += 1;
+            }
+            changed += walk(&mut obj.children, seen, taken, next);
+        }
+        changed
+    }
+    let mut taken: HashSet<u32> = all_ids(objects).into_iter().collect();
+    let mut next = taken.iter().max().map_or(0, |id| id.wrapping_add(1));
+    walk(objects, &mut HashSet::new(), &mut taken, &mut next)
+}
+
+/// Node count of every subtree, by the id of its root.
+fn subtree_sizes(objects: &[SceneObject]) -> HashMap<u32, usize> {
+    fn count(obj: &SceneObject, sizes: &mut HashMap<u32, usize>) -> usize {
+        let size = 1 + obj
+            .children
+            .iter()
+            .map(|child| count(child, sizes))
+            .sum::<usize>();
+        sizes.insert(obj.id, size);
+        size
+    }
+    let mut sizes = HashMap::new();
+    for obj in objects {
+        count(obj, &mut sizes);
+    }
+    sizes
+}
+
+fn collect_ids(objects: &[SceneObject], out: &mut Vec<u32>) {
+    for obj in objects {
+        out.push(obj.id);
+        collect_ids(&obj.children, out);
+    }
+}
+
+/// Ids from the root down to `id`, inclusive, or None if `id` isn't in the tree.
+fn path_to_node(objects: &[SceneObject], id: u32) -> Option<Vec<u32>> {
+    for obj in objects {
+        if obj.id == id {
+            return Some(vec![id]);
+        }
+        if let Some(mut path) = path_to_node(&obj.children, id) {
+            path.insert(0, obj.id);
+            return Some(path);
+        }
+    }
+    None
+}
+
+/// The id of the topmost node under `world_pos`, searching every root.
+fn node_at(
+    objects: &[SceneObject],
+    world_pos: Vector2,
+    positions: &HashMap<u32, Vector2>,
+) -> Option<u32> {
+    topmost_node(objects, &|obj| {
+        positions
+            .get(&obj.id)
+            .is_some_and(|pos| shape_contains_point(obj, *pos, world_pos))
+    })
+}
+
+/// The id of the node whose label is under `world_pos`.
+fn label_at(
+    rl: &RaylibHandle,
+    objects: &[SceneObject],
+    world_pos: Vector2,
+    positions: &HashMap<u32, Vector2>,
+) -> Option<u32> {
+    let font = rl.get_font_default();
+    topmost_node(objects, &|obj| {
+        positions.get(&obj.id).is_some_and(|pos| {
+            check_collision_point_rec(
+                world_pos,
+                label_rect(*pos, obj.size(), label_width(&font, &obj.text)),
+            )
+        })
+    })
+}
+
+/// The last node in draw order (a pre-order walk) that passes `hit`, so clicks land on
+/// whatever is drawn on top where nodes overlap.
+fn topmost_node(objects: &[SceneObject], hit: &impl Fn(&SceneObject) -> bool) -> Option<u32> {
+    objects
+        .iter()
+        .rev()
+        .find_map(|obj| topmost_node(&obj.children, hit).or_else(|| hit(obj).then_some(obj.id)))
+}
+
+/// The id of the parent whose connector to one of its children passes within
+/// `tolerance` of `world_pos`.
+fn connector_at(
+    objects: &[SceneObject],
     world_pos: Vector2,
     positions: &HashMap<u32, Vector2>,
+    style: ConnectorStyle,
+    direction: LayoutDirection,
+    tolerance: f32,
 ) -> Option<u32> {
-    if let Some(obj_pos) = positions.get(&obj.id) {
-        let dist = world_pos.distance_to(*obj_pos);
-        if dist < 20.0 {
-            // 20.0 is half the shape size
-            return Some(obj.id);
+    for obj in objects {
+        if let Some(from) = positions.get(&obj.id) {
+            for child in &obj.children {
+                if let Some(to) = positions.get(&child.id)
+                    && connector_points(style, direction, *from, *to)
+                        .windows(2)
+                        .any(|seg| distance_to_segment(world_pos, seg[0], seg[1]) <= tolerance)
+                {
+                    return Some(obj.id);
+                }
+            }
         }
-    }
-    for child in &obj.children {
-        if let Some(id) = find_clicked_object(child, world_pos, positions) {
+        if let Some(id) = connector_at(
+            &obj.children,
+            world_pos,
+            positions,
+            style,
+            direction,
+            tolerance,
+        ) {
             return Some(id);
         }
     }
     None
 }
 
+/// Shape-aware hit test for a node drawn at `center`.
+/// Hidden nodes are tested against the dashed ring they draw instead of their shape.
+fn shape_contains_point(obj: &SceneObject, center: Vector2, point: Vector2) -> bool {
+    let half = obj.size() / 2.0;
+    match obj.shape {
+        _ if !obj.visible || obj.glyph.is_some() => point.distance_to(center) < half,
+        // Stars and hexagons are close enough to their bounding circle.
+        Shape::Circle | Shape::Star | Shape::Hexagon => point.distance_to(center) < half,
+        Shape::Square => {
+            // Undo the square's rotation so the test becomes axis-aligned.
+            let local = rotate_point(point - center, -obj.angle());
+            local.x.abs() <= half && local.y.abs() <= half
+        }
+        Shape::Triangle => {
+            let [a, b, c] = triangle_vertices(center, obj.size(), obj.angle());
+            point_in_triangle(point, a, b, c)
+        }
+    }
+}
+
+/// World-space corners of a triangle node. Drawing and hit-testing both use this.
+fn triangle_vertices(center: Vector2, size: f32, rotation: f32) -> [Vector2; 3] {
+    [
+        Vector2::new(0.0, -size / 2.0),
+        Vector2::new(-size / 2.0, size / 2.0),
+        Vector2::new(size / 2.0, size / 2.0),
+    ]
+    .map(|p| rotate_point(p, rotation) + center)
+}
+
+/// World-space outline of a five-point star node, alternating outer and inner points.
+/// Points are in increasing angle order so each (center, next, current) triangle winds CCW.
+fn star_vertices(center: Vector2, size: f32, rotation: f32) -> [Vector2; 10] {
+    let outer = size / 2.0;
+    let inner = outer * 0.4;
+    std::array::from_fn(|i| {
+        let radius = if i % 2 == 0 { outer } else { inner };
+        let degrees = -90.0 + i as f32 * 36.0;
+        let (sin_a, cos_a) = degrees.to_radians().sin_cos();
+        rotate_point(Vector2::new(cos_a, sin_a) * radius, rotation) + center
+    })
+}
+
+fn point_in_triangle(point: Vector2, a: Vector2, b: Vector2, c: Vector2) -> bool {
+    let cross = |p1: Vector2, p2: Vector2, p3: Vector2| {
+        (p1.x - p3.x) * (p2.y - p3.y) - (p2.x - p3.x) * (p1.y - p3.y)
+    };
+    let d1 = cross(point, a, b);
+    let d2 = cross(point, b, c);
+    let d3 = cross(point, c, a);
+    let has_neg = d1 < 0.0 || d2 < 0.0 || d3 < 0.0;
+    let has_pos = d1 > 0.0 || d2 > 0.0 || d3 > 0.0;
+    !(has_neg && has_pos)
+}
+
+fn distance_to_segment(point: Vector2, a: Vector2, b: Vector2) -> f32 {
+    let ab = b - a;
+    let length_sqr = ab.length_sqr();
+    if length_sqr == 0.0 {
+        return point.distance_to(a);
+    }
+    let t = ((point - a).dot(ab) / length_sqr).clamp(0.0, 1.0);
+    point.distance_to(a + ab * t)
+}
+
+/// Even-odd test against the closed polygon through `points`.
+fn point_in_polygon(point: Vector2, points: &[Vector2]) -> bool {
+    let mut inside = false;
+    let mut j = points.len().wrapping_sub(1);
+    for i in 0..points.len() {
+        let (a, b) = (points[i], points[j]);
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < (b.x - a.x) * (point.y - a.y) / (b.y - a.y) + a.x
+        {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// The world area the camera shows, padded so labels and rings of nodes just off the
+/// edge still draw.
+fn visible_world_rect(camera: &Camera2D, screen_size: Vector2) -> Rectangle {
+    const CULL_MARGIN: f32 = 300.0;
+    let min = screen_to_world(Vector2::zero(), camera);
+    let max = screen_to_world(screen_size, camera);
+    Rectangle::new(
+        min.x - CULL_MARGIN,
+        min.y - CULL_MARGIN,
+        max.x - min.x + CULL_MARGIN * 2.0,
+        max.y - min.y + CULL_MARGIN * 2.0,
+    )
+}
+
+/// The axis-aligned box spanned by two points. Every connector style stays inside it.
+fn span_rect(a: Vector2, b: Vector2) -> Rectangle {
+    Rectangle::new(
+        a.x.min(b.x),
+        a.y.min(b.y),
+        (a.x - b.x).abs(),
+        (a.y - b.y).abs(),
+    )
+}
+
 fn screen_to_world(screen_pos: Vector2, camera: &Camera2D) -> Vector2 {
     (screen_pos - camera.offset) / camera.zoom + camera.target
 }
+
+fn world_to_screen(world_pos: Vector2, camera: &Camera2D) -> Vector2 {
+    (world_pos - camera.target) * camera.zoom + camera.offset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adding_after_loading_the_largest_id_wraps_to_a_free_one() {
+        let value = json::parse(r#"{"id": 4294967295, "children": [{"id": 0}]}"#).unwrap();
+        let root = scene_object_from_json(&value).unwrap();
+        let mut state = State::new();
+        state.requests.push(EditorRequest::ReplaceScene {
+            objects: vec![root],
+        });
+        process_editor_requests(&mut state);
+        state.requests.push(EditorRequest::AddChild {
+            parent_id: u32::MAX,
+            select: false,
+        });
+        state.requests.push(EditorRequest::AddChild {
+            parent_id: u32::MAX,
+            select: false,
+        });
+        process_editor_requests(&mut state);
+        let ids = all_ids(&state.scene_objects);
+        assert_eq!(ids.len(), 4);
+        assert_eq!(ids.iter().collect::<HashSet<_>>().len(), 4);
+        assert!(ids.contains(&1) && ids.contains(&2));
+    }
+}